@@ -0,0 +1,152 @@
+//! Compares the render time of the nesting the renderer started with, the
+//! row scheduler and tiles of several sizes and orders. Run with
+//! `cargo bench --bench scheduler`.
+
+use std::{sync::Arc, time::Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use raytracing::{
+    camera::{Camera, CameraArgs},
+    geometries::Sphere,
+    hittable::HittableList,
+    materials::{Dielectric, Lambertian, Material, Metal},
+    scheduler::{Scheduler, TileOrder},
+    vec::{Color, Vec3},
+};
+
+const RUNS: u32 = 3;
+
+fn main() {
+    let world = random_spheres();
+    let lights = HittableList::new();
+    let args = CameraArgs {
+        samples_per_pixel: 8,
+        image_width: 240,
+        max_depth: 10,
+        lookfrom: Vec3::new(13.0, 2.0, 3.0),
+        lookat: Vec3::new(0.0, 0.0, 0.0),
+        defocus_angle: 0.6,
+        focus_distance: 10.0,
+        fov: 20.0,
+        ..Default::default()
+    };
+
+    let camera = Camera::new(args.clone());
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        render_nested(&camera, &args, &world, &lights);
+    }
+    println!("Nested samples: {:?}", start.elapsed() / RUNS);
+
+    let mut schedulers = vec![Scheduler::Rows];
+    for size in [8, 16, 32, 64] {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            schedulers.push(Scheduler::Tiles { size, order });
+        }
+    }
+
+    for scheduler in schedulers {
+        let camera = Camera::new(CameraArgs {
+            scheduler,
+            ..args.clone()
+        });
+
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            camera.render_film(&world, &lights);
+        }
+        println!("{scheduler:?}: {:?}", start.elapsed() / RUNS);
+    }
+}
+
+/// The original rendering loop, parallel over the rows, over the pixels of
+/// every row and over the samples of every pixel, each sample building its
+/// own sampler. Samples are jittered over the pixel as they were then.
+fn render_nested(
+    camera: &Camera,
+    args: &CameraArgs,
+    world: &HittableList,
+    lights: &HittableList,
+) -> Vec<Color> {
+    let height = ((args.image_width as f64 / args.aspect_ratio) as u32).max(1);
+
+    (0..height)
+        .into_par_iter()
+        .flat_map(|row| {
+            (0..args.image_width).into_par_iter().map(move |column| {
+                let color: Color = (0..args.samples_per_pixel)
+                    .into_par_iter()
+                    .map(|index| {
+                        let mut sampler = args.sampler.build(args.samples_per_pixel, args.seed);
+                        sampler.start_pixel_sample((column, row), index);
+                        let (u, v) = sampler.get_pixel_2d();
+                        let ray = camera.get_ray(row, column, (u - 0.5, v - 0.5), sampler.as_mut());
+                        ray.map_or(Color::default(), |ray| {
+                            camera.ray_color(
+                                args.max_depth,
+                                &ray,
+                                world,
+                                lights,
+                                None,
+                                sampler.as_mut(),
+                            )
+                        })
+                    })
+                    .reduce(Color::default, |a, b| a + b);
+
+                color / args.samples_per_pixel as f64
+            })
+        })
+        .collect()
+}
+
+fn random_spheres() -> HittableList {
+    let mut world = HittableList::new();
+    let mut rng = StdRng::seed_from_u64(0);
+
+    world.add(Box::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
+    )));
+
+    for a in -11..=11 {
+        for b in -11..=11 {
+            let center = Vec3::new(
+                a as f64 + 0.9 * rng.gen::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.gen::<f64>(),
+            );
+            let material: Arc<dyn Material> = match rng.gen::<f64>() {
+                x if x < 0.8 => Arc::new(Lambertian::new(
+                    Vec3::random_with(&mut rng) * Vec3::random_with(&mut rng),
+                )),
+                x if x < 0.95 => Arc::new(Metal::new(
+                    Vec3::random_range_with(&mut rng, 0.5..1.0),
+                    rng.gen::<f64>() * 0.5,
+                )),
+                _ => Arc::new(Dielectric::new(1.5)),
+            };
+            world.add(Box::new(Sphere::new(center, 0.2, material)));
+        }
+    }
+
+    world.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Box::new(Sphere::new(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(Vec3::new(0.4, 0.2, 0.1))),
+    )));
+    world.add(Box::new(Sphere::new(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Metal::new(Vec3::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    world
+}
@@ -0,0 +1,273 @@
+use crate::{
+    camera::{Autofocus, Camera, CameraArgs},
+    hittable::HittableList,
+    vec::Vec3,
+};
+
+/// Camera parameters at `time` seconds into the animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub lookfrom: Vec3,
+    pub lookat: Vec3,
+    pub fov: f64,
+    pub focus_distance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Interpolation {
+    Linear,
+    /// Smooth curve through all keyframes, with the tangents of a Catmull-Rom
+    /// spline scaled to uneven spacing in time.
+    #[default]
+    CatmullRom,
+}
+
+/// Camera path through keyframes, replacing the animated parameters of
+/// `CameraArgs` frame by frame.
+#[derive(Debug, Clone)]
+pub struct CameraAnimation {
+    keyframes: Vec<Keyframe>,
+    pub interpolation: Interpolation,
+}
+
+impl CameraAnimation {
+    /// Panics without keyframes, with two at the same time or one at a
+    /// time that is not finite.
+    pub fn new(mut keyframes: Vec<Keyframe>, interpolation: Interpolation) -> Self {
+        assert!(!keyframes.is_empty(), "an animation needs keyframes");
+        assert!(
+            keyframes.iter().all(|key| key.time.is_finite()),
+            "keyframe times must be finite"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        for pair in keyframes.windows(2) {
+            assert!(
+                pair[0].time < pair[1].time,
+                "two keyframes at {} seconds",
+                pair[1].time
+            );
+        }
+        Self {
+            keyframes,
+            interpolation,
+        }
+    }
+
+    /// Time of the first and the last keyframe.
+    pub fn duration(&self) -> (f64, f64) {
+        (
+            self.keyframes.first().unwrap().time,
+            self.keyframes.last().unwrap().time,
+        )
+    }
+
+    /// Parameters at `time`, holding the first and last keyframe outside of
+    /// the animation.
+    pub fn at(&self, time: f64) -> Keyframe {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|key| key.time <= time);
+        if next == 0 {
+            return keys[0];
+        }
+        if next == keys.len() {
+            return keys[next - 1];
+        }
+
+        let (k1, k2) = (&keys[next - 1], &keys[next]);
+        let k0 = if next >= 2 { &keys[next - 2] } else { k1 };
+        let k3 = keys.get(next + 1).unwrap_or(k2);
+        let h = k2.time - k1.time;
+        let s = (time - k1.time) / h;
+
+        let interpolate = |value: fn(&Keyframe) -> Vec3| match self.interpolation {
+            Interpolation::Linear => value(k1) + (value(k2) - value(k1)) * s,
+            Interpolation::CatmullRom => {
+                let tangent =
+                    |a: &Keyframe, b: &Keyframe| (value(b) - value(a)) / (b.time - a.time);
+                hermite(
+                    value(k1),
+                    value(k2),
+                    tangent(k0, k2) * h,
+                    tangent(k1, k3) * h,
+                    s,
+                )
+            }
+        };
+        // The scalar parameters are interpolated together as one vector.
+        let lens = interpolate(|key| Vec3::new(key.fov, key.focus_distance, 0.0));
+        Keyframe {
+            time,
+            lookfrom: interpolate(|key| key.lookfrom),
+            lookat: interpolate(|key| key.lookat),
+            fov: lens.x,
+            focus_distance: lens.y,
+        }
+    }
+
+    /// Arguments of every frame at `fps` frames per second, from the first
+    /// to the last keyframe. Checkpoints get the frame number appended to
+    /// their path, so that every frame resumes from its own. `fps` must be
+    /// positive.
+    pub fn frames(&self, args: &CameraArgs, fps: f64) -> Vec<CameraArgs> {
+        assert!(fps > 0.0 && fps.is_finite(), "invalid frame rate {fps}");
+        let (start, end) = self.duration();
+        let count = ((end - start) * fps + 1e-9).floor() as u32 + 1;
+
+        (0..count)
+            .map(|frame| {
+                let key = self.at(start + frame as f64 / fps);
+                let mut checkpoint = args.checkpoint.clone();
+                if let Some(checkpoint) = &mut checkpoint {
+                    checkpoint
+                        .path
+                        .as_mut_os_string()
+                        .push(format!(".{frame:04}"));
+                }
+                CameraArgs {
+                    lookfrom: key.lookfrom,
+                    lookat: key.lookat,
+                    fov: key.fov,
+                    focus_distance: key.focus_distance,
+                    checkpoint,
+                    ..args.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Renders every frame into `directory/frame_0000`,
+    /// `directory/frame_0001` and so on, all sharing `world` and `lights`.
+    /// With `autofocus` every frame is focused on its own, overriding the
+    /// animated focus distance.
+    pub fn render_sequence(
+        &self,
+        args: &CameraArgs,
+        fps: f64,
+        autofocus: Option<Autofocus>,
+        directory: &str,
+        world: &HittableList,
+        lights: &HittableList,
+    ) {
+        for (frame, args) in self.frames(args, fps).into_iter().enumerate() {
+            let camera = match autofocus {
+                Some(autofocus) => Camera::focused(args, autofocus, world),
+                None => Camera::new(args),
+            };
+            camera.render_into(&frame_directory(directory, frame), world, lights);
+        }
+    }
+}
+
+fn frame_directory(directory: &str, frame: usize) -> String {
+    format!("{directory}/frame_{frame:04}")
+}
+
+/// Cubic Hermite curve from `p1` to `p2` with the tangents `m1` and `m2`,
+/// scaled to the parameter `s` running from 0 to 1.
+fn hermite(p1: Vec3, p2: Vec3, m1: Vec3, m2: Vec3, s: f64) -> Vec3 {
+    let s2 = s * s;
+    let s3 = s2 * s;
+    p1 * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + m1 * (s3 - 2.0 * s2 + s)
+        + p2 * (-2.0 * s3 + 3.0 * s2)
+        + m2 * (s3 - s2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::Checkpoint;
+
+    fn keyframe(time: f64, x: f64, fov: f64) -> Keyframe {
+        Keyframe {
+            time,
+            lookfrom: Vec3::new(x, 1.0, 0.0),
+            lookat: Vec3::new(x, 0.0, -1.0),
+            fov,
+            focus_distance: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let keyframes = vec![
+            keyframe(2.0, 2.0, 60.0),
+            keyframe(0.0, 0.0, 40.0),
+            keyframe(1.0, 1.0, 90.0),
+            keyframe(4.0, 4.0, 60.0),
+        ];
+
+        for interpolation in [Interpolation::Linear, Interpolation::CatmullRom] {
+            let animation = CameraAnimation::new(keyframes.clone(), interpolation);
+            for key in &keyframes {
+                assert_eq!(animation.at(key.time), *key);
+            }
+            assert_eq!(animation.at(-1.0).lookfrom.x, 0.0);
+            assert_eq!(animation.at(5.0).lookfrom.x, 4.0);
+
+            // Keyframes on a line with a constant speed stay on it, even
+            // though they are unevenly spaced.
+            for time in [0.25, 1.5, 3.0] {
+                let key = animation.at(time);
+                assert!((key.lookfrom - Vec3::new(time, 1.0, 0.0)).length() < 1e-12);
+                assert!((key.focus_distance - 1.0).abs() < 1e-12);
+            }
+        }
+
+        let linear = CameraAnimation::new(keyframes.clone(), Interpolation::Linear);
+        assert_eq!(linear.at(0.5).fov, 65.0);
+        // The spline rounds off the peak instead of turning sharply.
+        let smooth = CameraAnimation::new(keyframes, Interpolation::CatmullRom);
+        assert!(smooth.at(1.2).fov > linear.at(1.2).fov);
+    }
+
+    #[test]
+    fn test_frames() {
+        let animation = CameraAnimation::new(
+            vec![keyframe(0.0, 0.0, 40.0), keyframe(1.0, 1.0, 40.0)],
+            Interpolation::Linear,
+        );
+        let args = CameraArgs {
+            checkpoint: Some(Checkpoint::new("render.ckpt")),
+            ..Default::default()
+        };
+
+        let frames = animation.frames(&args, 4.0);
+        assert_eq!(frames.len(), 5);
+        assert_eq!(animation.frames(&args, 0.5).len(), 1);
+        assert_eq!(frames[2].lookfrom, Vec3::new(0.5, 1.0, 0.0));
+        assert_eq!(
+            frames[3].checkpoint.as_ref().unwrap().path.to_str(),
+            Some("render.ckpt.0003")
+        );
+    }
+
+    #[test]
+    fn test_frame_directories() {
+        assert_eq!(
+            frame_directory("renders/shot", 3),
+            "renders/shot/frame_0003"
+        );
+        assert_eq!(frame_directory("output", 12345), "output/frame_12345");
+    }
+
+    #[test]
+    #[should_panic(expected = "two keyframes at 1 seconds")]
+    fn test_duplicate_times_are_rejected() {
+        CameraAnimation::new(
+            vec![keyframe(1.0, 0.0, 40.0), keyframe(1.0, 1.0, 40.0)],
+            Interpolation::CatmullRom,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid frame rate")]
+    fn test_zero_fps_is_rejected() {
+        let animation = CameraAnimation::new(
+            vec![keyframe(0.0, 0.0, 40.0), keyframe(1.0, 1.0, 40.0)],
+            Interpolation::Linear,
+        );
+        animation.frames(&CameraArgs::default(), 0.0);
+    }
+}
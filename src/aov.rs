@@ -0,0 +1,163 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use crate::{
+    film::Film,
+    hittable::HitRecord,
+    ray::Ray,
+    vec::{Color, Vec3},
+};
+
+/// Running sums of the arbitrary output variables of one pixel. Geometric
+/// values are averaged over the samples that hit something, the lighting
+/// values are weighted by the reconstruction filter like the beauty image.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AovPixel {
+    pub depth_sum: f64,
+    pub normal_sum: Vec3,
+    pub albedo_sum: Color,
+    pub position_sum: Vec3,
+    pub hits: u32,
+    pub direct_sum: Color,
+    pub indirect_sum: Color,
+    pub weight_sum: f64,
+    /// IDs of the first sample of the pixel that hit something, plus one to
+    /// leave zero where nothing was hit.
+    pub object_id: u32,
+    pub material_id: u32,
+}
+
+impl AovPixel {
+    /// Adds one camera sample, `direct` is the light that reached the camera
+    /// after at most one bounce and `indirect` the rest.
+    pub fn add_sample(
+        &mut self,
+        hit: Option<(&Ray, &HitRecord)>,
+        direct: Color,
+        indirect: Color,
+        weight: f64,
+    ) {
+        self.direct_sum += direct * weight;
+        self.indirect_sum += indirect * weight;
+        self.weight_sum += weight;
+
+        let Some((ray, hit_record)) = hit else {
+            return;
+        };
+        if self.hits == 0 {
+            self.object_id = hit_record.object_id as u32 + 1;
+            self.material_id = hit_record.material_id as u32 + 1;
+        }
+        self.depth_sum += (hit_record.p - ray.orig).length();
+        self.normal_sum += hit_record.normal;
+        self.albedo_sum += hit_record.material.albedo();
+        self.position_sum += hit_record.p;
+        self.hits += 1;
+    }
+
+    fn average(&self, sum: Vec3) -> Vec3 {
+        if self.hits == 0 {
+            return Vec3::default();
+        }
+        sum / self.hits as f64
+    }
+
+    /// Distance from the camera to the first hit, zero where nothing was hit.
+    pub fn depth(&self) -> f64 {
+        if self.hits == 0 {
+            return 0.0;
+        }
+        self.depth_sum / self.hits as f64
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        let normal = self.average(self.normal_sum);
+        if normal.near_zero() {
+            return normal;
+        }
+        normal.unit_vector()
+    }
+
+    pub fn albedo(&self) -> Color {
+        self.average(self.albedo_sum)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.average(self.position_sum)
+    }
+
+    pub fn direct(&self) -> Color {
+        if self.weight_sum == 0.0 {
+            return Color::default();
+        }
+        self.direct_sum / self.weight_sum
+    }
+
+    pub fn indirect(&self) -> Color {
+        if self.weight_sum == 0.0 {
+            return Color::default();
+        }
+        self.indirect_sum / self.weight_sum
+    }
+}
+
+/// Name of an output image and the value it stores for every pixel.
+type Layer<T> = (&'static str, fn(&AovPixel) -> T);
+
+/// Writes every AOV of `film` and its sample counts to `directory` as PFM
+/// images, the format being the simplest one keeping the values unclamped.
+pub fn write_aovs(directory: &str, film: &Film) -> io::Result<()> {
+    let aovs = &film.aovs;
+    let vectors: [Layer<Vec3>; 6] = [
+        ("normal", AovPixel::normal),
+        ("albedo", AovPixel::albedo),
+        ("position", AovPixel::position),
+        ("direct", AovPixel::direct),
+        ("indirect", AovPixel::indirect),
+        ("beauty", |aov| aov.direct() + aov.indirect()),
+    ];
+    for (name, value) in vectors {
+        let values = aovs.iter().map(value).flat_map(|v| [v.x, v.y, v.z]);
+        write_pfm(&format!("{directory}/{name}.pfm"), film, 3, values)?;
+    }
+
+    let scalars: [Layer<f64>; 3] = [
+        ("depth", AovPixel::depth),
+        ("object_id", |aov| aov.object_id as f64),
+        ("material_id", |aov| aov.material_id as f64),
+    ];
+    for (name, value) in scalars {
+        write_pfm(
+            &format!("{directory}/{name}.pfm"),
+            film,
+            1,
+            aovs.iter().map(value),
+        )?;
+    }
+
+    let samples = film.pixels.iter().map(|pixel| pixel.samples as f64);
+    write_pfm(&format!("{directory}/samples.pfm"), film, 1, samples)
+}
+
+/// Portable float map with `channels` values per pixel given row by row from
+/// the top, PFM itself stores the rows from the bottom.
+fn write_pfm(
+    path: &str,
+    film: &Film,
+    channels: usize,
+    values: impl Iterator<Item = f64>,
+) -> io::Result<()> {
+    let values: Vec<f64> = values.collect();
+    let mut file = BufWriter::new(File::create(path)?);
+    let kind = if channels == 3 { "PF" } else { "Pf" };
+    write!(file, "{kind}\n{} {}\n-1.0\n", film.width, film.height)?;
+
+    for row in values.chunks(film.width as usize * channels).rev() {
+        for value in row {
+            file.write_all(&(*value as f32).to_le_bytes())?;
+        }
+    }
+    file.flush()
+}
@@ -1,17 +1,36 @@
 use std::{
     default,
+    error::Error,
+    fmt::{self, Debug},
     fs::{create_dir_all, File},
-    io::Write,
+    io::{ErrorKind, Write},
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
 
-use indicatif::ParallelProgressIterator;
-use rand::Rng;
+use indicatif::ProgressBar;
 use rayon::prelude::*;
 
 use crate::{
-    hittable::HittableList,
+    aov::{self, AovPixel},
+    checkpoint::{self, Checkpoint},
+    denoise::Denoiser,
+    film::{Film, Pixel},
+    filter::{Filter, FilterSampler},
+    hittable::{HitRecord, HittableList},
     interval::Interval,
+    projections::{
+        Aperture, CameraFrame, Equirectangular, Fisheye, FisheyeMapping, LensElement, LensError,
+        LensSystem, Orthographic, Perspective, RayGenerator,
+    },
     ray::Ray,
+    samplers::{Sampler, SamplerKind},
+    scheduler::{self, Scheduler, Tile},
+    tone_mapping::ToneMapping,
     vec::{Color, Vec3},
 };
 
@@ -29,6 +48,245 @@ pub struct CameraArgs {
     pub up: Vec3,
     pub defocus_angle: f64,
     pub focus_distance: f64,
+    /// Color returned by rays leaving the scene, `None` renders the sky gradient.
+    pub background: Option<Color>,
+    pub sampling_strategy: SamplingStrategy,
+    pub sampler: SamplerKind,
+    /// Seed for all sample values, renders with the same seed are identical.
+    pub seed: u64,
+    /// Distribute `samples_per_pixel` per pixel on average according to the
+    /// noise of each pixel instead of uniformly.
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Render in passes of doubling sample counts, rewriting the image after each.
+    pub progressive: bool,
+    /// Periodically save the accumulated samples to resume an interrupted render.
+    pub checkpoint: Option<Checkpoint>,
+    /// How the pixels are distributed between threads, does not change the image.
+    pub scheduler: Scheduler,
+    /// Only trace the pixels inside this window.
+    pub crop: Option<CropWindow>,
+    /// Weights the samples of a pixel, samples are spread over its radius.
+    pub filter: Filter,
+    /// Brightness change in stops applied before tone mapping the written images.
+    pub exposure: f64,
+    pub tone_mapping: ToneMapping,
+    /// Largest component a camera sample can contribute, scaling down the
+    /// fireflies of caustics at the cost of losing energy.
+    pub max_sample_radiance: Option<f64>,
+    /// Largest component of the light reflected at the bounces after the
+    /// first, clamping indirect light only.
+    pub max_indirect_radiance: Option<f64>,
+    /// Also accumulate depth, normal, albedo, IDs, position and the split of
+    /// direct and indirect light of the first hits, written as PFM images.
+    pub aovs: bool,
+    /// Denoise the written image and `render_pixels`, accumulating the AOVs
+    /// it is guided by.
+    pub denoiser: Option<Denoiser>,
+    pub projection: Projection,
+    /// Shape of the defocus blur of the perspective and orthographic projections.
+    pub aperture: Aperture,
+    /// Clips the aperture off axis to a cat's eye like a lens barrel does,
+    /// see `Perspective::with_aperture`.
+    pub cat_eye: f64,
+    /// Render a left and a right eye view, each `image_width` wide, into one
+    /// image with the left eye on the left.
+    pub stereo: Option<StereoRig>,
+    /// Moves the image of the perspective and orthographic projections by
+    /// this fraction of its width and height, right and up, without turning
+    /// the camera. Shifting up a level camera keeps verticals parallel.
+    pub lens_shift: (f64, f64),
+    /// Degrees the plane in focus is turned around the horizontal and
+    /// vertical axes, moving its top and right side away from the camera.
+    pub focal_plane_tilt: (f64, f64),
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum Projection {
+    /// Pinhole or thin lens camera with the vertical field of view `fov`.
+    #[default]
+    Perspective,
+    /// Parallel rays along the view direction, `viewport_height` is the
+    /// height of the visible area in world units.
+    Orthographic { viewport_height: f64 },
+    /// Full sphere of directions, longitude along the width and latitude
+    /// along the height with the view direction in the center. With `stereo`
+    /// set to an interocular distance, the image holds an omnidirectional
+    /// stereo pair with the left eye on top.
+    Equirectangular { stereo: Option<f64> },
+    /// Circular fisheye filling the shorter image side with `fov` degrees,
+    /// which may exceed 180 for equidistant mapping.
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    /// Rays traced through the surfaces of a real lens focused at
+    /// `focus_distance`, with the film diagonal in millimetres.
+    LensSystem {
+        elements: Vec<LensElement>,
+        film_diagonal: f64,
+    },
+    /// A projection from outside this crate, see `CameraFrame` for the
+    /// camera placement implied by `lookfrom`, `lookat` and `up`. A stereo
+    /// rig moves the origins of its rays to the eyes.
+    Custom(Arc<dyn RayGenerator>),
+}
+
+/// Two cameras `interocular` apart along the right vector of the camera,
+/// with parallel view directions. The perspective projection shifts their
+/// images so that points `convergence` in front of the camera appear at the
+/// same position to both eyes, other projections ignore it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoRig {
+    pub interocular: f64,
+    pub convergence: f64,
+    pub layout: StereoLayout,
+}
+
+impl StereoRig {
+    pub fn new(interocular: f64, convergence: f64) -> Self {
+        Self {
+            interocular,
+            convergence,
+            layout: StereoLayout::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StereoLayout {
+    /// One image with both views next to each other.
+    #[default]
+    SideBySide,
+    /// The images and AOVs of each eye in the `left` and `right` directories.
+    Separate,
+}
+
+/// Focuses at the depth of the scene behind a pixel, see `Camera::focused`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Autofocus {
+    /// Column and row of the pixel to focus through, the image center if
+    /// `None`. For a stereo rig the pixel is in the image of one eye.
+    pub pixel: Option<(u32, u32)>,
+}
+
+/// Why `Camera::try_new` rejects its arguments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    Lens(LensError),
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraError::Lens(error) => write!(f, "unusable lens system: {error}"),
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+impl From<LensError> for CameraError {
+    fn from(error: LensError) -> Self {
+        Self::Lens(error)
+    }
+}
+
+/// Pixel region of the full image, clipped to the image when the camera is built.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropWindow {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Write the full image with everything outside the window black instead
+    /// of only the window.
+    pub full_frame: bool,
+}
+
+impl CropWindow {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            full_frame: false,
+        }
+    }
+}
+
+/// Samples per pixel of the passes between checkpoints of uniform renders.
+const CHECKPOINT_PASS_SAMPLES: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Standard error of the gamma encoded pixel value at which a pixel stops
+    /// receiving samples.
+    pub noise_threshold: f64,
+    /// Samples every pixel gets before its noise is estimated, also the size
+    /// of the batches handed out afterwards.
+    pub min_samples: u32,
+    pub max_samples: u32,
+}
+
+impl AdaptiveSampling {
+    /// Samples a noisy pixel gets per pass.
+    fn batch(&self) -> u32 {
+        self.min_samples.max(2)
+    }
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            noise_threshold: 0.01,
+            min_samples: 16,
+            max_samples: 4096,
+        }
+    }
+}
+
+/// How direct lighting from the lights passed to `Camera::render` is estimated.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SamplingStrategy {
+    /// Only follow the scattered rays, ignoring the lights.
+    Bsdf,
+    /// Sample a light with a shadow ray at every non-specular bounce.
+    Lights,
+    /// Combine both strategies with the power heuristic.
+    #[default]
+    Mis,
+}
+
+/// Factor scaling `color` down so that no component exceeds `max`, keeping the hue.
+fn clamp_scale(color: Color, max: f64) -> f64 {
+    let largest = color.x.max(color.y).max(color.z);
+    if largest > max {
+        max / largest
+    } else {
+        1.0
+    }
+}
+
+/// Light arriving along a ray, split by the number of bounces it took.
+#[derive(Debug, Clone, Copy, Default)]
+struct Radiance {
+    /// Emitted by the surface the ray hits, or the background.
+    emitted: Color,
+    /// Reflected at that surface straight from an emitter.
+    direct: Color,
+    /// Reflected after further bounces.
+    indirect: Color,
+}
+
+impl Radiance {
+    fn total(&self) -> Color {
+        self.emitted + self.direct + self.indirect
+    }
+}
+
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    if other_pdf <= 0.0 {
+        return 1.0;
+    }
+    pdf * pdf / (pdf * pdf + other_pdf * other_pdf)
 }
 
 impl default::Default for CameraArgs {
@@ -46,82 +304,304 @@ impl default::Default for CameraArgs {
             up: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_distance: 1.0,
+            background: None,
+            sampling_strategy: SamplingStrategy::default(),
+            sampler: SamplerKind::default(),
+            seed: 0,
+            adaptive: None,
+            progressive: false,
+            checkpoint: None,
+            scheduler: Scheduler::default(),
+            crop: None,
+            filter: Filter::default(),
+            exposure: 0.0,
+            tone_mapping: ToneMapping::default(),
+            max_sample_radiance: None,
+            max_indirect_radiance: None,
+            aovs: false,
+            denoiser: None,
+            projection: Projection::default(),
+            aperture: Aperture::default(),
+            cat_eye: 0.0,
+            stereo: None,
+            lens_shift: (0.0, 0.0),
+            focal_plane_tilt: (0.0, 0.0),
         }
     }
 }
 
 pub struct Camera {
+    /// Width of the film, twice `eye_width` for a stereo rig.
     image_width: u32,
     image_height: u32,
-    origin: Vec3,
-    pixel_00_location: Vec3,
-    pixel_delta_u: Vec3,
-    pixel_delta_v: Vec3,
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
-    defocus_angle: f64,
+    eye_width: u32,
+    /// One ray generator per eye, from left to right.
+    ray_generators: Vec<Arc<dyn RayGenerator>>,
+    stereo: Option<StereoRig>,
     samples_per_pixel: u32,
     max_depth: u32,
+    background: Option<Color>,
+    sampling_strategy: SamplingStrategy,
+    sampler: SamplerKind,
+    seed: u64,
+    adaptive: Option<AdaptiveSampling>,
+    progressive: bool,
+    checkpoint: Option<Checkpoint>,
+    scheduler: Scheduler,
+    crop: Option<CropWindow>,
+    filter: FilterSampler,
+    exposure: f64,
+    tone_mapping: ToneMapping,
+    max_sample_radiance: Option<f64>,
+    max_indirect_radiance: Option<f64>,
+    aovs: bool,
+    denoiser: Option<Denoiser>,
+    /// Hash of the `CameraArgs` that change the samples taken, see `args_hash`.
+    args_hash: u64,
 }
 
 impl Camera {
+    /// Panics where `try_new` fails.
     pub fn new(args: CameraArgs) -> Self {
+        Self::try_new(args).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Builds the camera, failing on a projection that cannot be used.
+    pub fn try_new(args: CameraArgs) -> Result<Self, CameraError> {
         let image_height = ((args.image_width as f64 / args.aspect_ratio) as i32).max(1);
 
-        let theta = args.fov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * args.focus_distance;
-        let viewport_width = viewport_height * (args.image_width as f64 / image_height as f64);
+        let frame = CameraFrame::new(
+            args.lookfrom,
+            args.lookat,
+            args.up,
+            args.image_width,
+            image_height as u32,
+        );
+        let ray_generators: Vec<_> = match args.stereo {
+            None => vec![ray_generator(&args, &frame, 0.0)?],
+            Some(rig) => [-0.5, 0.5]
+                .into_iter()
+                .map(|side| ray_generator(&args, &frame, side * rig.interocular))
+                .collect::<Result<_, _>>()?,
+        };
+        let image_width = args.image_width * ray_generators.len() as u32;
+
+        Ok(Self {
+            image_width,
+            image_height: image_height as u32,
+            eye_width: args.image_width,
+            ray_generators,
+            stereo: args.stereo,
+            samples_per_pixel: args.samples_per_pixel,
+            max_depth: args.max_depth,
+            background: args.background,
+            sampling_strategy: args.sampling_strategy,
+            sampler: args.sampler,
+            seed: args.seed,
+            adaptive: args.adaptive,
+            progressive: args.progressive,
+            checkpoint: args.checkpoint.clone(),
+            scheduler: args.scheduler,
+            filter: FilterSampler::new(args.filter),
+            exposure: args.exposure,
+            tone_mapping: args.tone_mapping,
+            max_sample_radiance: args.max_sample_radiance,
+            max_indirect_radiance: args.max_indirect_radiance,
+            aovs: args.aovs,
+            denoiser: args.denoiser,
+            crop: args.crop.map(|crop| {
+                let x = crop.x.min(image_width - 1);
+                let y = crop.y.min(image_height as u32 - 1);
+                CropWindow {
+                    x,
+                    y,
+                    width: crop.width.clamp(1, image_width - x),
+                    height: crop.height.clamp(1, image_height as u32 - y),
+                    ..crop
+                }
+            }),
+            args_hash: args_hash(&args),
+        })
+    }
 
-        let camera_origin = args.lookfrom;
+    /// Builds the camera with `focus_distance` replaced by the distance along
+    /// the view direction of the first hit in `world` through the autofocus
+    /// pixel. Without a hit in front of the camera the focus stays unchanged.
+    /// Panics where `new` does or the pixel is outside of the image.
+    pub fn focused(mut args: CameraArgs, autofocus: Autofocus, world: &HittableList) -> Self {
+        let pinhole = Camera::new(CameraArgs {
+            defocus_angle: 0.0,
+            stereo: None,
+            ..args.clone()
+        });
+        let film = match autofocus.pixel {
+            Some((column, row)) => {
+                assert!(
+                    column < pinhole.eye_width && row < pinhole.image_height,
+                    "autofocus pixel {:?} outside of the image",
+                    (column, row)
+                );
+                (column as f64 + 0.5, row as f64 + 0.5)
+            }
+            None => (
+                0.5 * pinhole.eye_width as f64,
+                0.5 * pinhole.image_height as f64,
+            ),
+        };
 
-        let w = (args.lookfrom - args.lookat).unit_vector();
-        let u = args.up.unit_vector().cross(w);
-        let v = w.cross(u);
+        let ray = pinhole.ray_generators[0].generate_ray(film, (0.5, 0.5));
+        let hit = ray.and_then(|ray| world.hit(&ray, Interval::new(0.001, f64::INFINITY)));
+        if let Some(hit) = hit {
+            // Projections seeing behind the camera can hit behind the view plane.
+            let view = (args.lookat - args.lookfrom).unit_vector();
+            let depth = (hit.p - args.lookfrom).dot(view);
+            if depth > 0.0 {
+                args.focus_distance = depth;
+            }
+        }
+        Self::new(args)
+    }
 
-        let viewport_u = u * viewport_width;
-        let viewport_v = -v * viewport_height;
+    /// Traces `ray` into the world. `bsdf_pdf` is the density with which the
+    /// previous vertex sampled `ray` when it also sampled the lights directly.
+    pub fn ray_color(
+        &self,
+        depth: u32,
+        ray: &Ray,
+        world: &HittableList,
+        lights: &HittableList,
+        bsdf_pdf: Option<f64>,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        self.trace(depth, ray, world, lights, bsdf_pdf, sampler)
+            .0
+            .total()
+    }
 
-        let pixel_delta_u = viewport_u / args.image_width as f64;
-        let pixel_delta_v = viewport_v / image_height as f64;
+    /// `ray_color` split into its parts, along with the hit of `ray`.
+    fn trace(
+        &self,
+        depth: u32,
+        ray: &Ray,
+        world: &HittableList,
+        lights: &HittableList,
+        bsdf_pdf: Option<f64>,
+        sampler: &mut dyn Sampler,
+    ) -> (Radiance, Option<HitRecord>) {
+        if depth == 0 {
+            return (Radiance::default(), None);
+        }
+        let Some(hit_record) = world.hit(ray, Interval::new(0.001, f64::INFINITY)) else {
+            let background = Radiance {
+                emitted: self.background_color(ray),
+                ..Default::default()
+            };
+            return (background, None);
+        };
 
-        let defocus_disk_radius =
-            (args.defocus_angle / 2.0).to_radians().tan() * args.focus_distance;
-        let defocus_disk_u = defocus_disk_radius * u;
-        let defocus_disk_v = defocus_disk_radius * v;
+        let mut emitted = hit_record.material.emitted(ray, &hit_record);
+        if let Some(bsdf_pdf) = bsdf_pdf {
+            if emitted != Color::default() {
+                let light_pdf = lights.pdf_value(ray.orig, ray.dir);
+                emitted = emitted * self.bsdf_weight(bsdf_pdf, light_pdf);
+            }
+        }
 
-        let viewport_upper_left =
-            camera_origin - args.focus_distance * w - 0.5 * viewport_u - 0.5 * viewport_v;
+        // Draw the same dimensions at every vertex, whichever get used.
+        let (u_light, u_light_point) = (sampler.get_1d(), sampler.get_2d());
+        let (u_bsdf, u_bsdf_direction) = (sampler.get_1d(), sampler.get_2d());
 
-        let pixel_00_location = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+        let wo = -ray.dir.unit_vector();
+        let material = &hit_record.material;
 
-        Self {
-            image_width: args.image_width,
-            image_height: image_height as u32,
-            origin: camera_origin,
-            samples_per_pixel: args.samples_per_pixel,
-            max_depth: args.max_depth,
-            pixel_00_location,
-            pixel_delta_u,
-            pixel_delta_v,
-            defocus_disk_u,
-            defocus_disk_v,
-            defocus_angle: args.defocus_angle,
+        let direct = if self.sampling_strategy == SamplingStrategy::Bsdf
+            || lights.is_empty()
+            || material.is_specular()
+        {
+            None
+        } else {
+            Some(self.sample_light(&hit_record, wo, world, lights, u_light, u_light_point))
+        };
+
+        let mut radiance = Radiance {
+            emitted,
+            direct: direct.unwrap_or_default(),
+            indirect: Color::default(),
+        };
+        if let Some(sample) = material.sample(&hit_record, wo, u_bsdf, u_bsdf_direction) {
+            let bsdf_pdf = match direct {
+                Some(_) if !sample.is_specular => Some(sample.pdf),
+                _ => None,
+            };
+            let scattered_ray = Ray::new(hit_record.p, sample.direction);
+            let (incoming, _) =
+                self.trace(depth - 1, &scattered_ray, world, lights, bsdf_pdf, sampler);
+
+            let weight = sample.weight(hit_record.normal);
+            radiance.direct += weight * incoming.emitted;
+            radiance.indirect = weight * (incoming.direct + incoming.indirect);
         }
+
+        if let Some(max) = self.max_indirect_radiance {
+            if depth < self.max_depth {
+                let scale = clamp_scale(radiance.direct + radiance.indirect, max);
+                radiance.direct = radiance.direct * scale;
+                radiance.indirect = radiance.indirect * scale;
+            }
+        }
+
+        (radiance, Some(hit_record))
     }
 
-    pub fn ray_color(depth: u32, ray: &Ray, world: &HittableList) -> Color {
-        if depth == 0 {
+    fn sample_light(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vec3,
+        world: &HittableList,
+        lights: &HittableList,
+        u_light: f64,
+        u: (f64, f64),
+    ) -> Color {
+        let wi = lights.random(hit_record.p, u_light, u).unit_vector();
+        let bsdf = hit_record.material.eval(hit_record, wo, wi);
+        if bsdf == Color::default() {
             return Color::default();
         }
-        if let Some(hit_record) = world.hit(ray, Interval::new(0.001, f64::INFINITY)) {
-            if let Some((attenuation, scattered_ray)) =
-                hit_record.material.scatter(ray, &hit_record)
-            {
-                return attenuation * Self::ray_color(depth - 1, &scattered_ray, world);
-            } else {
-                return Color::default();
+
+        let light_pdf = lights.pdf_value(hit_record.p, wi);
+        if light_pdf <= 0.0 {
+            return Color::default();
+        }
+
+        let shadow_ray = Ray::new(hit_record.p, wi);
+        let light = world
+            .hit(&shadow_ray, Interval::new(0.001, f64::INFINITY))
+            .map(|record| record.material.emitted(&shadow_ray, &record))
+            .unwrap_or_default();
+
+        let weight = match self.sampling_strategy {
+            SamplingStrategy::Mis => {
+                power_heuristic(light_pdf, hit_record.material.pdf(hit_record, wo, wi))
             }
+            _ => 1.0,
+        };
+
+        bsdf * wi.dot(hit_record.normal).abs() * light * weight / light_pdf
+    }
+
+    /// Weight of emission found by BSDF sampling at a vertex that also sampled the lights.
+    fn bsdf_weight(&self, bsdf_pdf: f64, light_pdf: f64) -> f64 {
+        match self.sampling_strategy {
+            SamplingStrategy::Bsdf => 1.0,
+            SamplingStrategy::Lights if light_pdf > 0.0 => 0.0,
+            SamplingStrategy::Lights => 1.0,
+            SamplingStrategy::Mis => power_heuristic(bsdf_pdf, light_pdf),
+        }
+    }
+
+    fn background_color(&self, ray: &Ray) -> Color {
+        if let Some(background) = self.background {
+            return background;
         }
 
         let unit_dir = ray.dir.unit_vector();
@@ -129,75 +609,590 @@ impl Camera {
         (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
     }
 
-    pub fn get_ray(&self, row: u32, column: u32) -> Ray {
-        let mut rng = rand::thread_rng();
-        let (rand_u, rand_v) = (rng.gen_range(-0.5..=0.5), rng.gen_range(-0.5..=0.5));
-        let pixel_sample = self.pixel_00_location
-            + self.pixel_delta_v * (row as f64 + rand_v)
-            + self.pixel_delta_u * (column as f64 + rand_u);
+    /// Ray through the point `offset` away from the center of a pixel, `None`
+    /// where the projection does not cover the image.
+    pub fn get_ray(
+        &self,
+        row: u32,
+        column: u32,
+        offset: (f64, f64),
+        sampler: &mut dyn Sampler,
+    ) -> Option<Ray> {
+        let lens_sample = sampler.get_2d();
+        let eye = (column / self.eye_width).min(self.ray_generators.len() as u32 - 1);
+        let column = column - eye * self.eye_width;
+        let film = (column as f64 + 0.5 + offset.0, row as f64 + 0.5 + offset.1);
+        self.ray_generators[eye as usize].generate_ray(film, lens_sample)
+    }
+
+    pub fn render(&self, world: &HittableList, lights: &HittableList) {
+        self.render_into("output", world, lights);
+    }
+
+    /// Renders and writes the images to `directory` instead of `output`.
+    pub fn render_into(&self, directory: &str, world: &HittableList, lights: &HittableList) {
+        create_dir_all(directory).unwrap();
+
+        let film = self.render_progressive(world, lights, |film| {
+            if self.progressive {
+                self.write_images(directory, film);
+            }
+            ControlFlow::Continue(())
+        });
+        self.write_images(directory, &film);
+    }
+
+    fn write_images(&self, directory: &str, film: &Film) {
+        let film = self.framed(film);
+        match self.stereo {
+            Some(StereoRig {
+                layout: StereoLayout::Separate,
+                ..
+            }) => {
+                let film = film.full_frame(self.image_width, self.image_height);
+                for (eye, name) in ["left", "right"].into_iter().enumerate() {
+                    let directory = format!("{directory}/{name}");
+                    create_dir_all(&directory).unwrap();
+                    let tile = Tile {
+                        x: eye as u32 * self.eye_width,
+                        y: 0,
+                        width: self.eye_width,
+                        height: self.image_height,
+                    };
+                    let (pixels, aovs) = film.tile_pixels(&tile);
+                    let eye_film = Film {
+                        pixels,
+                        aovs,
+                        ..Film::new(self.eye_width, self.image_height)
+                    };
+                    self.write_film(&directory, &eye_film);
+                }
+            }
+            _ => self.write_film(directory, &film),
+        }
+    }
+
+    fn write_film(&self, directory: &str, film: &Film) {
+        write_ppm(
+            &format!("{directory}/image.ppm"),
+            film,
+            film.pixels
+                .iter()
+                .map(|pixel| self.display_color(pixel.color()).to_bytes_string()),
+        );
+
+        if self.adaptive.is_some() {
+            let max_samples = film.pixels.iter().map(|pixel| pixel.samples).max();
+            let max_samples = max_samples.unwrap_or_default().max(1) as f64;
+            write_ppm(
+                &format!("{directory}/samples.ppm"),
+                film,
+                film.pixels.iter().map(|pixel| {
+                    let value = (pixel.samples as f64 / max_samples * 255.0) as u8;
+                    format!("{value} {value} {value}")
+                }),
+            );
+        }
+
+        if self.aovs {
+            aov::write_aovs(directory, film).unwrap();
+        }
+    }
+
+    /// Applies the exposure and the tone mapping, the result is still linear.
+    pub fn display_color(&self, color: Color) -> Color {
+        self.tone_mapping.apply(color * 2f64.powf(self.exposure))
+    }
 
-        let origin = if self.defocus_angle > 0.0 {
-            let random = Vec3::random_in_unit_circle();
-            self.origin + random.x * self.defocus_disk_u + random.y * self.defocus_disk_v
+    /// Linear pixel colors in row-major order. The result only depends on the
+    /// scene and the camera arguments, not on how rayon schedules the work.
+    pub fn render_pixels(&self, world: &HittableList, lights: &HittableList) -> Vec<Color> {
+        self.framed(&self.render_film(world, lights)).colors()
+    }
+
+    /// The film as it is written, denoised if enabled and the crop window
+    /// alone or in the full frame. The eyes of a stereo rig are denoised
+    /// separately, so that the filter does not reach across their seam.
+    fn framed(&self, film: &Film) -> Film {
+        let film = match self.denoiser {
+            Some(denoiser) => {
+                let mut denoised = film.clone();
+                for tile in self.eye_tiles(film) {
+                    if tile.width == 0 {
+                        continue;
+                    }
+                    let (pixels, aovs) = film.tile_pixels(&tile);
+                    let eye = denoiser.denoise(&Film {
+                        x: film.x + tile.x,
+                        y: film.y + tile.y,
+                        width: tile.width,
+                        height: tile.height,
+                        pixels,
+                        aovs,
+                    });
+                    denoised.set_tile_pixels(&tile, &eye.pixels, &eye.aovs);
+                }
+                denoised
+            }
+            None => film.clone(),
+        };
+        match self.crop {
+            Some(crop) if crop.full_frame => film.full_frame(self.image_width, self.image_height),
+            _ => film,
+        }
+    }
+
+    /// The part of `film` seen by every eye, from left to right, in film
+    /// coordinates. Eyes outside of a crop window get an empty tile.
+    fn eye_tiles(&self, film: &Film) -> Vec<Tile> {
+        (0..self.ray_generators.len() as u32)
+            .map(|eye| {
+                let start = (eye * self.eye_width).clamp(film.x, film.x + film.width);
+                let end = ((eye + 1) * self.eye_width).clamp(film.x, film.x + film.width);
+                Tile {
+                    x: start - film.x,
+                    y: 0,
+                    width: end - start,
+                    height: film.height,
+                }
+            })
+            .collect()
+    }
+
+    fn eye(&self, column: u32) -> usize {
+        (column / self.eye_width).min(self.ray_generators.len() as u32 - 1) as usize
+    }
+
+    fn new_film(&self) -> Film {
+        let film = match self.crop {
+            Some(crop) => Film::region(crop.x, crop.y, crop.width, crop.height),
+            None => Film::new(self.image_width, self.image_height),
+        };
+        if self.aovs || self.denoiser.is_some() {
+            film.with_aovs()
         } else {
-            self.origin
-        };
-        let direction = pixel_sample - origin;
-
-        Ray {
-            orig: origin,
-            dir: direction,
-        }
-    }
-
-    pub fn render(&self, world: &HittableList) {
-        create_dir_all("output").unwrap();
-        let mut file = File::create("output/image.ppm").unwrap();
-
-        write!(
-            &mut file,
-            "P3\n{} {}\n255\n",
-            self.image_width, self.image_height
-        )
-        .unwrap();
-
-        // for row in (0..self.image_height).progress() {
-        //     for column in 0..self.image_width {
-        //         let mut color = Color::default();
-
-        //         for _ in 0..self.samples_per_pixel {
-        //             let ray = self.get_ray(row, column);
-        //             color += self.ray_color(self.max_depth, &ray, world);
-        //         }
-        //         color = color / self.samples_per_pixel as f64;
-        //         writeln!(&mut file, "{}", color.to_bytes_string()).unwrap();
-        //     }
-        // }
-
-        // Parallel implementation using rayon
-
-        let colors: Vec<Color> = (0..self.image_height)
-            .into_par_iter()
-            .progress()
-            .flat_map(|row| {
-                (0..self.image_width).into_par_iter().map(move |column| {
-                    let color: Color = (0..self.samples_per_pixel)
-                        .into_par_iter()
-                        .map(|_| {
-                            let ray = self.get_ray(row, column);
-                            Self::ray_color(self.max_depth, &ray, world)
+            film
+        }
+    }
+
+    pub fn render_film(&self, world: &HittableList, lights: &HittableList) -> Film {
+        self.render_progressive(world, lights, |_| ControlFlow::Continue(()))
+    }
+
+    /// Renders in passes and calls `on_pass` with the accumulated film after
+    /// each of them, stopping early when it breaks. Passes double the samples
+    /// per pixel when `CameraArgs::progressive` is set, otherwise there is a
+    /// single pass unless sampling is adaptive or checkpoints are written.
+    /// The film only covers the crop window if there is one.
+    pub fn render_progressive(
+        &self,
+        world: &HittableList,
+        lights: &HittableList,
+        mut on_pass: impl FnMut(&Film) -> ControlFlow<()>,
+    ) -> Film {
+        assert!(
+            lights.is_sampleable(),
+            "lights must implement Hittable::pdf_value and random"
+        );
+        let scene_hash = checkpoint::scene_hash(&format!("{world:?}{lights:?}{}", self.args_hash));
+        let mut film = self.resume(scene_hash).unwrap_or_else(|| self.new_film());
+        let budget = film.pixels.len() as u64 * self.samples_per_pixel as u64;
+        let progress = ProgressBar::new(budget);
+        progress.set_position(film.total_samples());
+
+        let mut last_checkpoint = Instant::now();
+        let mut after_pass = |film: &Film| {
+            if let Some(checkpoint) = &self.checkpoint {
+                if last_checkpoint.elapsed() >= checkpoint.interval {
+                    save_checkpoint(checkpoint, film, scene_hash);
+                    last_checkpoint = Instant::now();
+                }
+            }
+            on_pass(film)
+        };
+
+        match self.adaptive {
+            None => {
+                let pixel_samples = film.pixels.iter().map(|pixel| pixel.samples);
+                let mut total = pixel_samples.min().unwrap_or_default();
+                while total < self.samples_per_pixel {
+                    let remaining = self.samples_per_pixel - total;
+                    let samples = if self.progressive {
+                        total.max(1).min(remaining)
+                    } else if self.checkpoint.is_some() {
+                        CHECKPOINT_PASS_SAMPLES.min(remaining)
+                    } else {
+                        remaining
+                    };
+                    self.render_pass(&mut film, samples, |_, _| true, world, lights, &progress);
+                    total += samples;
+
+                    if after_pass(&film).is_break() {
+                        break;
+                    }
+                }
+            }
+            Some(adaptive) => {
+                // Hand out batches of samples to the pixels that are still
+                // noisy until they converge or the budget of a uniform render
+                // is used up. Every eye of a stereo rig has its own budget.
+                let batch = adaptive.batch();
+                let is_noisy = |pixel: &Pixel| {
+                    pixel.samples < adaptive.max_samples
+                        && (pixel.samples < adaptive.min_samples
+                            || pixel.display_error() > adaptive.noise_threshold)
+                };
+                let eyes = self.eye_tiles(&film);
+
+                loop {
+                    let has_budget: Vec<bool> = eyes
+                        .iter()
+                        .map(|tile| {
+                            let (pixels, _) = film.tile_pixels(tile);
+                            let samples: u64 =
+                                pixels.iter().map(|pixel| pixel.samples as u64).sum();
+                            samples < pixels.len() as u64 * self.samples_per_pixel as u64
                         })
-                        .reduce(Color::default, |a, b| a + b);
+                        .collect();
+                    let is_active = |column: u32, pixel: &Pixel| {
+                        has_budget[self.eye(column)] && is_noisy(pixel)
+                    };
+                    let width = film.width as usize;
+                    let any_active =
+                        film.pixels.iter().enumerate().any(|(index, pixel)| {
+                            is_active(film.x + (index % width) as u32, pixel)
+                        });
+                    if !any_active {
+                        break;
+                    }
 
-                    color / self.samples_per_pixel as f64
-                })
-            })
-            .collect();
+                    self.render_pass(&mut film, batch, is_active, world, lights, &progress);
+
+                    if after_pass(&film).is_break() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(checkpoint) = &self.checkpoint {
+            save_checkpoint(checkpoint, &film, scene_hash);
+        }
+
+        progress.finish();
+        film
+    }
+
+    /// The film of the checkpoint, `None` to start over when there is none
+    /// or it cannot be used.
+    fn resume(&self, scene_hash: u64) -> Option<Film> {
+        let checkpoint = self
+            .checkpoint
+            .as_ref()
+            .filter(|checkpoint| checkpoint.resume)?;
+        match checkpoint::load(&checkpoint.path, scene_hash, self.new_film()) {
+            Ok(film) => Some(film),
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => {
+                eprintln!(
+                    "cannot resume from {:?}, starting over: {error}",
+                    checkpoint.path
+                );
+                None
+            }
+        }
+    }
+
+    /// Adds `samples` samples to every pixel for which `is_active` holds,
+    /// given the column of the pixel in the image.
+    fn render_pass(
+        &self,
+        film: &mut Film,
+        samples: u32,
+        is_active: impl Fn(u32, &Pixel) -> bool + Sync,
+        world: &HittableList,
+        lights: &HittableList,
+        progress: &ProgressBar,
+    ) {
+        // Parallel implementation using rayon. Samples of a pixel are summed
+        // sequentially so that the floating point result is reproducible.
 
-        for color in colors {
-            writeln!(&mut file, "{}", color.to_bytes_string()).unwrap();
+        // Adaptive sampling stops most pixels long before `max_samples`, so
+        // the stratified sampler fills its strata with every batch instead.
+        let stratified_samples = self
+            .adaptive
+            .map_or(self.samples_per_pixel, |adaptive| adaptive.batch());
+        let width = film.width as usize;
+        let (x, y) = (film.x, film.y);
+
+        match self.scheduler {
+            Scheduler::Rows => {
+                let mut aovs: Vec<Option<&mut AovPixel>> = if film.aovs.is_empty() {
+                    film.pixels.iter().map(|_| None).collect()
+                } else {
+                    film.aovs.iter_mut().map(Some).collect()
+                };
+                film.pixels
+                    .par_chunks_mut(width)
+                    .zip(aovs.par_chunks_mut(width))
+                    .enumerate()
+                    .for_each(|(row, (pixels, aovs))| {
+                        pixels
+                            .par_iter_mut()
+                            .zip(aovs.par_iter_mut())
+                            .enumerate()
+                            .filter(|(column, (pixel, _))| is_active(x + *column as u32, pixel))
+                            .for_each(|(column, (pixel, aov))| {
+                                let mut sampler = self.sampler.build(stratified_samples, self.seed);
+                                self.render_pixel(
+                                    (pixel, aov.as_deref_mut()),
+                                    (x + column as u32, y + row as u32),
+                                    samples,
+                                    sampler.as_mut(),
+                                    world,
+                                    lights,
+                                );
+                                progress.inc(samples as u64);
+                            });
+                    })
+            }
+            Scheduler::Tiles { size, order } => {
+                let tiles = scheduler::tiles(film.width, film.height, size, order);
+                let next_tile = AtomicUsize::new(0);
+
+                // Every worker takes the next tile in order until none are left,
+                // so the tiles are started in `order` whatever their cost.
+                let rendered: Vec<_> = (0..rayon::current_num_threads())
+                    .into_par_iter()
+                    .flat_map_iter(|_| {
+                        let film = &*film;
+                        let next_tile = &next_tile;
+                        let tiles = &tiles;
+                        let is_active = &is_active;
+                        let mut sampler = self.sampler.build(stratified_samples, self.seed);
+
+                        std::iter::from_fn(move || {
+                            let tile = *tiles.get(next_tile.fetch_add(1, Ordering::Relaxed))?;
+                            let (mut pixels, mut aovs) = film.tile_pixels(&tile);
+                            for (index, pixel) in pixels.iter_mut().enumerate() {
+                                let column = x + tile.x + index as u32 % tile.width;
+                                if !is_active(column, pixel) {
+                                    continue;
+                                }
+                                let row = y + tile.y + index as u32 / tile.width;
+                                self.render_pixel(
+                                    (pixel, aovs.get_mut(index)),
+                                    (column, row),
+                                    samples,
+                                    sampler.as_mut(),
+                                    world,
+                                    lights,
+                                );
+                                progress.inc(samples as u64);
+                            }
+                            Some((tile, pixels, aovs))
+                        })
+                    })
+                    .collect();
+
+                for (tile, pixels, aovs) in rendered {
+                    film.set_tile_pixels(&tile, &pixels, &aovs);
+                }
+            }
         }
     }
+
+    fn render_pixel(
+        &self,
+        (pixel, mut aov): (&mut Pixel, Option<&mut AovPixel>),
+        (column, row): (u32, u32),
+        samples: u32,
+        sampler: &mut dyn Sampler,
+        world: &HittableList,
+        lights: &HittableList,
+    ) {
+        for _ in 0..samples {
+            sampler.start_pixel_sample((column, row), pixel.samples);
+            let filter_sample = self.filter.sample(sampler.get_pixel_2d());
+            let ray = self.get_ray(row, column, filter_sample.offset, sampler);
+            let (radiance, hit_record) = match &ray {
+                Some(ray) => self.trace(self.max_depth, ray, world, lights, None, sampler),
+                None => (Radiance::default(), None),
+            };
+
+            let color = radiance.total();
+            let scale = self
+                .max_sample_radiance
+                .map_or(1.0, |max| clamp_scale(color, max));
+
+            if let Some(aov) = aov.as_deref_mut() {
+                aov.add_sample(
+                    ray.as_ref().zip(hit_record.as_ref()),
+                    (radiance.emitted + radiance.direct) * scale,
+                    radiance.indirect * scale,
+                    filter_sample.weight,
+                );
+            }
+            pixel.add_sample(color * scale, filter_sample.weight);
+        }
+    }
+}
+
+/// Hash of the arguments that change the samples taken. A checkpoint can be
+/// resumed with another sample budget, schedule or output settings, which
+/// are left out. Every field is named so that new ones are not missed.
+fn args_hash(args: &CameraArgs) -> u64 {
+    let CameraArgs {
+        aspect_ratio,
+        image_width,
+        origin,
+        focal_length,
+        samples_per_pixel: _,
+        max_depth,
+        fov,
+        lookfrom,
+        lookat,
+        up,
+        defocus_angle,
+        focus_distance,
+        background,
+        sampling_strategy,
+        sampler,
+        seed,
+        adaptive: _,
+        progressive: _,
+        checkpoint: _,
+        scheduler: _,
+        crop,
+        filter,
+        exposure: _,
+        tone_mapping: _,
+        max_sample_radiance,
+        max_indirect_radiance,
+        aovs: _,
+        denoiser: _,
+        projection,
+        aperture,
+        cat_eye,
+        stereo,
+        lens_shift,
+        focal_plane_tilt,
+    } = args;
+    let fields: [&dyn Debug; 25] = [
+        aspect_ratio,
+        image_width,
+        origin,
+        focal_length,
+        max_depth,
+        fov,
+        lookfrom,
+        lookat,
+        up,
+        defocus_angle,
+        focus_distance,
+        background,
+        sampling_strategy,
+        sampler,
+        seed,
+        crop,
+        filter,
+        max_sample_radiance,
+        max_indirect_radiance,
+        projection,
+        aperture,
+        cat_eye,
+        stereo,
+        lens_shift,
+        focal_plane_tilt,
+    ];
+    checkpoint::scene_hash(&format!("{fields:?}"))
+}
+
+/// Writes a checkpoint, reporting a failure instead of losing the render.
+fn save_checkpoint(checkpoint: &Checkpoint, film: &Film, scene_hash: u64) {
+    if let Err(error) = checkpoint::save(&checkpoint.path, film, scene_hash) {
+        eprintln!("cannot write checkpoint {:?}: {error}", checkpoint.path);
+    }
+}
+
+/// Builds the projection of `args` for a camera moved `eye_offset` along its
+/// right vector.
+fn ray_generator(
+    args: &CameraArgs,
+    frame: &CameraFrame,
+    eye_offset: f64,
+) -> Result<Arc<dyn RayGenerator>, CameraError> {
+    let frame = &CameraFrame {
+        origin: frame.origin + eye_offset * frame.basis.u,
+        ..*frame
+    };
+    let ray_generator: Arc<dyn RayGenerator> = match &args.projection {
+        Projection::Perspective => {
+            // Moves the image of the convergence plane back by the eye offset.
+            let stereo_shift = args.stereo.map_or(0.0, |rig| {
+                let width = 2.0 * (args.fov.to_radians() / 2.0).tan() * frame.aspect_ratio();
+                -eye_offset / (rig.convergence * width)
+            });
+            let (shift_x, shift_y) = args.lens_shift;
+            Arc::new(
+                Perspective::new(frame, args.fov, args.focus_distance, args.defocus_angle)
+                    .with_aperture(args.aperture.clone(), args.cat_eye)
+                    .with_shift((shift_x + stereo_shift, shift_y))
+                    .with_tilt(args.focal_plane_tilt),
+            )
+        }
+        Projection::Orthographic { viewport_height } => Arc::new(
+            Orthographic::new(
+                frame,
+                *viewport_height,
+                args.focus_distance,
+                args.defocus_angle,
+            )
+            .with_aperture(args.aperture.clone(), args.cat_eye)
+            .with_shift(args.lens_shift)
+            .with_tilt(args.focal_plane_tilt),
+        ),
+        Projection::Equirectangular { stereo } => Arc::new(Equirectangular::new(frame, *stereo)),
+        Projection::Fisheye { fov, mapping } => Arc::new(Fisheye::new(frame, *fov, *mapping)),
+        Projection::LensSystem {
+            elements,
+            film_diagonal,
+        } => Arc::new(LensSystem::new(
+            frame,
+            elements.clone(),
+            *film_diagonal,
+            args.focus_distance,
+        )?),
+        Projection::Custom(ray_generator) if eye_offset == 0.0 => ray_generator.clone(),
+        Projection::Custom(ray_generator) => Arc::new(MovedEye {
+            ray_generator: ray_generator.clone(),
+            offset: eye_offset * frame.basis.u,
+        }),
+    };
+    Ok(ray_generator)
+}
+
+/// A projection from outside this crate moved to one eye of a stereo rig.
+#[derive(Debug)]
+struct MovedEye {
+    ray_generator: Arc<dyn RayGenerator>,
+    offset: Vec3,
+}
+
+impl RayGenerator for MovedEye {
+    fn generate_ray(&self, film: (f64, f64), lens: (f64, f64)) -> Option<Ray> {
+        let ray = self.ray_generator.generate_ray(film, lens)?;
+        Some(Ray::new(ray.orig + self.offset, ray.dir))
+    }
+}
+
+fn write_ppm(path: &str, film: &Film, pixels: impl Iterator<Item = String>) {
+    let mut file = File::create(path).unwrap();
+    write!(&mut file, "P3\n{} {}\n255\n", film.width, film.height).unwrap();
+
+    for pixel in pixels {
+        writeln!(&mut file, "{pixel}").unwrap();
+    }
 }
 
 impl Default for Camera {
@@ -206,3 +1201,706 @@ impl Default for Camera {
         Self::new(args)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        geometries::{Plane, Quad, Sphere},
+        materials::{Dielectric, DiffuseLight, Lambertian, Metal},
+        samplers::Independent,
+        scheduler::TileOrder,
+    };
+
+    // One plate of Veach's glossy plates scene, seen in the mirror direction
+    // of a spherical light of the same power regardless of its radius.
+    fn glossy_plate_variance(fuzz: f64, radius: f64, strategy: SamplingStrategy) -> f64 {
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+
+        let plate = Arc::new(Metal::new(Vec3::new(0.7, 0.7, 0.7), fuzz));
+        world.add(Box::new(Quad::new(
+            Vec3::new(-5.0, 0.0, -5.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            plate,
+        )));
+
+        let light = Arc::new(DiffuseLight::new(
+            Color::new(1.0, 1.0, 1.0) / radius.powi(2),
+        ));
+        let center = Vec3::new(0.0, 2.0, -6.0);
+        world.add(Box::new(Sphere::new(center, radius, light.clone())));
+        lights.add(Box::new(Sphere::new(center, radius, light)));
+
+        let camera = Camera::new(CameraArgs {
+            background: Some(Color::default()),
+            sampling_strategy: strategy,
+            ..Default::default()
+        });
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 3.0), Vec3::new(0.0, -1.0, -3.0));
+
+        let n = 20_000;
+        let mut sampler = Independent::new(1);
+        let samples: Vec<f64> = (0..n)
+            .map(|index| {
+                sampler.start_pixel_sample((0, 0), index);
+                camera
+                    .ray_color(3, &ray, &world, &lights, None, &mut sampler)
+                    .x
+            })
+            .collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64
+    }
+
+    #[test]
+    fn test_mis_sharp_plate_large_light() {
+        let lights = glossy_plate_variance(0.02, 1.0, SamplingStrategy::Lights);
+        let mis = glossy_plate_variance(0.02, 1.0, SamplingStrategy::Mis);
+
+        assert!(mis < 0.01 * lights, "mis {mis}, lights {lights}");
+    }
+
+    #[test]
+    fn test_mis_rough_plate_small_light() {
+        let bsdf = glossy_plate_variance(0.9, 0.2, SamplingStrategy::Bsdf);
+        let mis = glossy_plate_variance(0.9, 0.2, SamplingStrategy::Mis);
+
+        assert!(mis < 0.01 * bsdf, "mis {mis}, bsdf {bsdf}");
+    }
+
+    #[test]
+    fn test_adaptive_sampling_stops_on_flat_background() {
+        let camera = Camera::new(CameraArgs {
+            image_width: 16,
+            samples_per_pixel: 64,
+            adaptive: Some(AdaptiveSampling::default()),
+            ..Default::default()
+        });
+        let film = camera.render_film(&HittableList::new(), &HittableList::new());
+
+        assert!(film.pixels.iter().all(|pixel| pixel.samples == 16));
+    }
+
+    #[test]
+    fn test_progressive_passes_match_single_pass() {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -1.5),
+            0.5,
+            Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.5)),
+        )));
+        let args = CameraArgs {
+            image_width: 16,
+            samples_per_pixel: 8,
+            ..Default::default()
+        };
+
+        let mut pass_samples = Vec::new();
+        let progressive = Camera::new(CameraArgs {
+            progressive: true,
+            ..args.clone()
+        })
+        .render_progressive(&world, &HittableList::new(), |film| {
+            pass_samples.push(film.pixels[0].samples);
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(pass_samples, [1, 2, 4, 8]);
+        assert_eq!(
+            progressive,
+            Camera::new(args).render_film(&world, &HittableList::new())
+        );
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -1.5),
+            0.5,
+            Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.5)),
+        )));
+        let lights = HittableList::new();
+
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.bin", std::process::id()));
+        let mut checkpoint = Checkpoint::new(&path);
+        checkpoint.interval = std::time::Duration::ZERO;
+        let args = CameraArgs {
+            image_width: 16,
+            samples_per_pixel: 40,
+            aovs: true,
+            ..Default::default()
+        };
+        let checkpointed = CameraArgs {
+            checkpoint: Some(checkpoint),
+            ..args.clone()
+        };
+        let camera = Camera::new(checkpointed.clone());
+
+        // Stop after the first pass as if the render had been killed.
+        let interrupted = camera.render_progressive(&world, &lights, |_| ControlFlow::Break(()));
+        assert!(interrupted.pixels.iter().all(|pixel| pixel.samples == 16));
+
+        let resumed = camera.render_film(&world, &lights);
+        assert_eq!(resumed, Camera::new(args).render_film(&world, &lights));
+
+        // A larger budget continues from the samples of the finished render.
+        let more_samples = Camera::new(CameraArgs {
+            samples_per_pixel: 48,
+            ..checkpointed
+        });
+        let mut passes = 0;
+        let continued = more_samples.render_progressive(&world, &lights, |_| {
+            passes += 1;
+            ControlFlow::Continue(())
+        });
+        assert_eq!(passes, 1);
+        assert!(continued.pixels.iter().all(|pixel| pixel.samples == 48));
+
+        let scene_hash =
+            checkpoint::scene_hash(&format!("{world:?}{lights:?}{}", camera.args_hash));
+        let other_scene = checkpoint::load(&path, 0, camera.new_film()).unwrap_err();
+        assert_eq!(other_scene.kind(), ErrorKind::InvalidData);
+        let other_region = checkpoint::load(&path, scene_hash, Film::new(8, 8)).unwrap_err();
+        assert_eq!(other_region.kind(), ErrorKind::InvalidData);
+
+        // A damaged checkpoint is reported and the render starts over.
+        std::fs::write(&path, b"RTCK").unwrap();
+        let restarted = camera.render_film(&world, &lights);
+        assert!(restarted.pixels.iter().all(|pixel| pixel.samples == 40));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_render_independent_of_threads_and_scheduler() {
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+
+        let light = Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 2.0, -2.0),
+            0.5,
+            light.clone(),
+        )));
+        lights.add(Box::new(Sphere::new(Vec3::new(0.0, 2.0, -2.0), 0.5, light)));
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, -100.5, -1.0),
+            100.0,
+            Arc::new(Metal::new(Vec3::new(0.8, 0.8, 0.8), 0.3)),
+        )));
+
+        let render = |threads, seed, scheduler| {
+            let camera = Camera::new(CameraArgs {
+                image_width: 32,
+                samples_per_pixel: 8,
+                seed,
+                scheduler,
+                ..Default::default()
+            });
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| camera.render_pixels(&world, &lights))
+        };
+
+        let tiles = Scheduler::Tiles {
+            size: 5,
+            order: TileOrder::Hilbert,
+        };
+        let single = render(1, 5, Scheduler::Rows);
+        assert_eq!(single, render(4, 5, Scheduler::Rows));
+        assert_eq!(single, render(4, 5, tiles));
+        assert_ne!(single, render(4, 6, tiles));
+    }
+
+    #[test]
+    fn test_crop_window_matches_full_render() {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.3)),
+        )));
+        let lights = HittableList::new();
+
+        let render = |crop| {
+            Camera::new(CameraArgs {
+                image_width: 32,
+                samples_per_pixel: 4,
+                crop,
+                ..Default::default()
+            })
+            .render_pixels(&world, &lights)
+        };
+
+        let full = render(None);
+        let window = CropWindow::new(5, 3, 10, 6);
+        let cropped = render(Some(window));
+        let framed = render(Some(CropWindow {
+            full_frame: true,
+            ..window
+        }));
+
+        assert_eq!(cropped.len(), 60);
+        assert_eq!(framed.len(), full.len());
+        for (index, color) in full.iter().enumerate() {
+            let (row, column) = (index / 32, index % 32);
+            if (3..9).contains(&row) && (5..15).contains(&column) {
+                assert_eq!(*color, cropped[(row - 3) * 10 + column - 5]);
+                assert_eq!(*color, framed[index]);
+            } else {
+                assert_eq!(framed[index], Color::default());
+            }
+        }
+    }
+
+    #[test]
+    fn test_clamp_limits_sample_radiance() {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(Dielectric::new(1.5)),
+        )));
+        let lights = HittableList::new();
+
+        let render = |max_sample_radiance, max_indirect_radiance| {
+            Camera::new(CameraArgs {
+                image_width: 16,
+                samples_per_pixel: 4,
+                background: Some(Color::new(8.0, 4.0, 2.0)),
+                max_sample_radiance,
+                max_indirect_radiance,
+                ..Default::default()
+            })
+            .render_pixels(&world, &lights)
+        };
+
+        let clamped = render(Some(2.0), None);
+        assert!(clamped.iter().all(|color| color.x <= 2.0 + 1e-9));
+        assert!(clamped
+            .iter()
+            .all(|color| (color.x - 2.0 * color.y).abs() < 1e-9));
+
+        // The background seen directly is not indirect light.
+        let indirect = render(None, Some(1.0));
+        let brightest = indirect.iter().map(|color| color.x).fold(0.0, f64::max);
+        assert_eq!(brightest, 8.0);
+        assert!(indirect.iter().any(|color| color.x <= 1.0));
+    }
+
+    #[test]
+    fn test_aovs_of_first_hit() {
+        let mut world = HittableList::new();
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            0.5,
+            Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.6))),
+        )));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 3.0, 0.0),
+            0.5,
+            Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))),
+        )));
+
+        let camera = Camera::new(CameraArgs {
+            image_width: 17,
+            fov: 60.0,
+            samples_per_pixel: 8,
+            aovs: true,
+            ..Default::default()
+        });
+        let film = camera.render_film(&world, &lights);
+        assert_eq!((film.width, film.height), (17, 9));
+
+        let center = film.aovs[4 * 17 + 8];
+        assert_eq!(center.object_id, 1);
+        assert_ne!(center.material_id, 0);
+        assert!((center.albedo() - Color::new(0.2, 0.4, 0.6)).length() < 1e-9);
+        assert!(center.normal().dot(Vec3::new(0.0, 0.0, 1.0)) > 0.95);
+        assert!((1.49..1.6).contains(&center.depth()));
+
+        let corner = film.aovs[0];
+        assert_eq!((corner.object_id, corner.hits, corner.depth()), (0, 0, 0.0));
+
+        for (pixel, aov) in film.pixels.iter().zip(&film.aovs) {
+            let difference = pixel.color() - aov.direct() - aov.indirect();
+            assert!(difference.length() < 1e-9);
+        }
+        assert!(center.direct().length() > 0.0);
+
+        // Materials are told apart by identity rather than by their parameters.
+        let shared = Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.6)));
+        let equal = Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.6)));
+        let mut world = HittableList::new();
+        for (x, material) in [(-1.0, shared.clone()), (0.0, equal), (1.0, shared)] {
+            world.add(Box::new(Sphere::new(
+                Vec3::new(x, 0.0, -2.0),
+                0.4,
+                material,
+            )));
+        }
+        let ray_to = |x| Ray::new(Vec3::default(), Vec3::new(x, 0.0, -2.0));
+        let hit = |ray: &Ray| world.hit(ray, Interval::new(0.001, f64::INFINITY));
+        let ids = [-1.0, 0.0, 1.0].map(|x| hit(&ray_to(x)).unwrap().material_id);
+        assert_eq!(ids, [0, 1, 0]);
+
+        // The IDs come from the first sample that hits, not the first sample.
+        let mut aov = AovPixel::default();
+        aov.add_sample(None, Color::default(), Color::default(), 1.0);
+        let ray = ray_to(1.0);
+        let record = hit(&ray).unwrap();
+        aov.add_sample(
+            Some((&ray, &record)),
+            Color::default(),
+            Color::default(),
+            1.0,
+        );
+        assert_eq!((aov.object_id, aov.material_id), (3, 1));
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let camera = Camera::new(CameraArgs {
+            image_width: 20,
+            lookfrom: Vec3::new(1.0, 2.0, 3.0),
+            lookat: Vec3::new(0.0, 0.0, 0.0),
+            focus_distance: 2.0,
+            projection: Projection::Orthographic {
+                viewport_height: 4.0,
+            },
+            ..Default::default()
+        });
+        let mut sampler = Independent::new(0);
+        let view = Vec3::new(-1.0, -2.0, -3.0).unit_vector();
+
+        let top_left = camera.get_ray(0, 0, (-0.5, -0.5), &mut sampler).unwrap();
+        let bottom_right = camera.get_ray(11, 20, (-0.5, -0.5), &mut sampler).unwrap();
+        for ray in [&top_left, &bottom_right] {
+            assert!((ray.dir.unit_vector() - view).length() < 1e-12);
+        }
+
+        let extent = bottom_right.orig - top_left.orig;
+        assert!(extent.dot(view).abs() < 1e-12);
+        assert!(
+            (extent.length_squared() - (4.0f64.powi(2) + (80.0f64 / 11.0).powi(2))).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_panoramic_projections() {
+        let mut sampler = Independent::new(0);
+        let forward = Vec3::new(0.0, 0.0, -1.0);
+
+        let equirectangular = Camera::new(CameraArgs {
+            image_width: 40,
+            aspect_ratio: 2.0,
+            projection: Projection::Equirectangular { stereo: Some(0.1) },
+            ..Default::default()
+        });
+        // Centers of the left eye panorama on top and the right one below.
+        let left = equirectangular
+            .get_ray(4, 20, (-0.5, 0.5), &mut sampler)
+            .unwrap();
+        let right = equirectangular
+            .get_ray(14, 20, (-0.5, 0.5), &mut sampler)
+            .unwrap();
+        for ray in [&left, &right] {
+            assert!((ray.dir.unit_vector() - forward).length() < 1e-12);
+        }
+        assert!((left.orig - Vec3::new(-0.05, 0.0, 0.0)).length() < 1e-12);
+        assert!((right.orig - Vec3::new(0.05, 0.0, 0.0)).length() < 1e-12);
+
+        // A quarter turn to the right at three quarters of the width.
+        let side = equirectangular
+            .get_ray(4, 30, (-0.5, 0.5), &mut sampler)
+            .unwrap();
+        assert!((side.dir.unit_vector() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let fisheye = Camera::new(CameraArgs {
+                image_width: 20,
+                aspect_ratio: 1.0,
+                projection: Projection::Fisheye {
+                    fov: 180.0,
+                    mapping,
+                },
+                ..Default::default()
+            });
+            let center = fisheye.get_ray(10, 10, (-0.5, -0.5), &mut sampler).unwrap();
+            assert!((center.dir.unit_vector() - forward).length() < 1e-12);
+
+            // The edge of the image circle is 90 degrees off the view direction.
+            let edge = fisheye.get_ray(10, 19, (0.5, -0.5), &mut sampler).unwrap();
+            assert!((edge.dir.unit_vector() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+            assert!(fisheye.get_ray(0, 0, (0.0, 0.0), &mut sampler).is_none());
+        }
+    }
+
+    #[test]
+    fn test_cat_eye_clips_aperture_off_axis() {
+        let camera = Camera::new(CameraArgs {
+            image_width: 16,
+            aspect_ratio: 1.0,
+            defocus_angle: 2.0,
+            aperture: Aperture::Polygon {
+                blades: 5,
+                rotation: 0.0,
+            },
+            cat_eye: 1.0,
+            ..Default::default()
+        });
+        let mut sampler = Independent::new(0);
+
+        let mut blocked = [0, 0];
+        for (i, (row, column)) in [(8, 8), (0, 0)].into_iter().enumerate() {
+            for _ in 0..256 {
+                if camera
+                    .get_ray(row, column, (-0.5, -0.5), &mut sampler)
+                    .is_none()
+                {
+                    blocked[i] += 1;
+                }
+            }
+        }
+        assert_eq!(blocked[0], 0);
+        assert!(blocked[1] > 32);
+    }
+
+    #[test]
+    fn test_stereo_eyes_converge() {
+        let convergence = 3.0;
+        let camera = Camera::new(CameraArgs {
+            image_width: 16,
+            aspect_ratio: 1.0,
+            fov: 90.0,
+            stereo: Some(StereoRig::new(0.064, convergence)),
+            ..Default::default()
+        });
+        let film = camera.render_film(&HittableList::new(), &HittableList::new());
+        assert_eq!((film.width, film.height), (32, 16));
+
+        // The image centers of both eyes look at the same point on the
+        // convergence plane from their own positions.
+        let mut sampler = Independent::new(0);
+        let left = camera.get_ray(8, 8, (-0.5, -0.5), &mut sampler).unwrap();
+        let right = camera.get_ray(8, 24, (-0.5, -0.5), &mut sampler).unwrap();
+        assert!((left.orig - Vec3::new(-0.032, 0.0, 0.0)).length() < 1e-12);
+        assert!((right.orig - Vec3::new(0.032, 0.0, 0.0)).length() < 1e-12);
+        for ray in [left, right] {
+            let target = ray.at(-convergence / ray.dir.z);
+            assert!((target - Vec3::new(0.0, 0.0, -convergence)).length() < 1e-12);
+        }
+
+        // Custom projections are moved to the eyes as they are.
+        let frame = CameraFrame::new(
+            Vec3::default(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            16,
+            16,
+        );
+        let custom = Camera::new(CameraArgs {
+            image_width: 16,
+            aspect_ratio: 1.0,
+            stereo: Some(StereoRig::new(0.064, convergence)),
+            projection: Projection::Custom(Arc::new(Perspective::new(&frame, 90.0, 1.0, 0.0))),
+            ..Default::default()
+        });
+        let left = custom.get_ray(8, 8, (-0.5, -0.5), &mut sampler).unwrap();
+        let right = custom.get_ray(8, 24, (-0.5, -0.5), &mut sampler).unwrap();
+        assert!((right.orig - left.orig - Vec3::new(0.064, 0.0, 0.0)).length() < 1e-12);
+        assert_eq!(left.dir, right.dir);
+    }
+
+    #[test]
+    fn test_stereo_eyes_denoised_separately() {
+        let camera = Camera::new(CameraArgs {
+            image_width: 8,
+            aspect_ratio: 1.0,
+            stereo: Some(StereoRig::new(0.064, 3.0)),
+            denoiser: Some(Denoiser::default()),
+            ..Default::default()
+        });
+
+        // A white left eye next to a black right eye on the same surface.
+        let mut film = camera.new_film();
+        for (index, (pixel, aov)) in film.pixels.iter_mut().zip(&mut film.aovs).enumerate() {
+            let brightness = if index % 16 < 8 { 1.0 } else { 0.0 };
+            pixel.add_sample(Color::new(brightness, brightness, brightness), 1.0);
+            aov.add_sample(None, Color::default(), Color::default(), 1.0);
+            aov.albedo_sum = Color::new(0.5, 0.5, 0.5);
+            aov.normal_sum = Vec3::new(0.0, 0.0, 1.0);
+            aov.hits = 1;
+        }
+
+        let colors = camera.framed(&film).colors();
+        for (index, color) in colors.iter().enumerate() {
+            let expected = if index % 16 < 8 { 1.0 } else { 0.0 };
+            assert!((color.x - expected).abs() < 1e-12, "{index}: {color:?}");
+        }
+    }
+
+    #[test]
+    fn test_tilt_shift() {
+        let focus_distance = 4.0;
+        let camera = Camera::new(CameraArgs {
+            image_width: 16,
+            aspect_ratio: 1.0,
+            fov: 60.0,
+            defocus_angle: 5.0,
+            focus_distance,
+            lens_shift: (0.0, 0.5),
+            focal_plane_tilt: (30.0, 0.0),
+            ..Default::default()
+        });
+        let mut sampler = Independent::new(0);
+
+        // The rays of a pixel in the middle column meet where they cross the
+        // plane x = 0, in the tilted plane in focus that leans back.
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        let mut focus_point = |row| {
+            let points: Vec<Vec3> = (0..4)
+                .map(|_| {
+                    let ray = camera.get_ray(row, 8, (-0.5, -0.5), &mut sampler).unwrap();
+                    ray.at(-ray.orig.x / ray.dir.x)
+                })
+                .collect();
+            for point in &points {
+                assert!((*point - points[0]).length() < 1e-9);
+                assert!((point.y * sin + (point.z + focus_distance) * cos).abs() < 1e-9);
+            }
+            points[0]
+        };
+
+        // Shifted up by half the image without turning the camera, the
+        // bottom edge looks straight ahead at the focus distance.
+        let bottom = focus_point(16);
+        assert!((bottom - Vec3::new(0.0, 0.0, -focus_distance)).length() < 1e-9);
+        let top = focus_point(0);
+        assert!(top.y > 0.0 && top.z < -focus_distance);
+    }
+
+    #[test]
+    fn test_autofocus() {
+        let mut world = HittableList::new();
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        world.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -3.0),
+            0.5,
+            material,
+        )));
+
+        let args = CameraArgs {
+            image_width: 16,
+            aspect_ratio: 1.0,
+            fov: 40.0,
+            defocus_angle: 4.0,
+            ..Default::default()
+        };
+        let mut sampler = Independent::new(0);
+        // Depth at which the rays through the image center meet.
+        let mut focus_depth = |camera: Camera| {
+            let ray = camera.get_ray(8, 8, (-0.5, -0.5), &mut sampler).unwrap();
+            let t = ray.orig.x / -ray.dir.x;
+            -ray.at(t).z
+        };
+
+        let focused = Camera::focused(args.clone(), Autofocus::default(), &world);
+        assert!((focus_depth(focused) - 2.5).abs() < 1e-9);
+
+        // A pixel next to the sphere sees nothing, keeping the focus distance.
+        let corner = Autofocus {
+            pixel: Some((0, 0)),
+        };
+        let missed = Camera::focused(args.clone(), corner, &world);
+        assert!((focus_depth(missed) - 1.0).abs() < 1e-9);
+
+        // So does a hit behind the camera, seen by a fisheye looking back.
+        let mut behind = HittableList::new();
+        behind.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            1.5,
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        )));
+        let fisheye = CameraArgs {
+            projection: Projection::Fisheye {
+                fov: 360.0,
+                mapping: FisheyeMapping::Equidistant,
+            },
+            ..args
+        };
+        let edge = Autofocus {
+            pixel: Some((15, 8)),
+        };
+        let camera = Camera::focused(fisheye.clone(), edge, &behind);
+        assert_eq!(camera.args_hash, Camera::new(fisheye).args_hash);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the image")]
+    fn test_autofocus_pixel_outside_of_image() {
+        let args = CameraArgs {
+            image_width: 16,
+            aspect_ratio: 1.0,
+            ..Default::default()
+        };
+        let pixel = Autofocus {
+            pixel: Some((16, 0)),
+        };
+        Camera::focused(args, pixel, &HittableList::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "lights must implement")]
+    fn test_unsampleable_lights_are_rejected() {
+        let mut lights = HittableList::new();
+        lights.add(Box::new(Plane::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Arc::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0))),
+        )));
+        let camera = Camera::new(CameraArgs {
+            image_width: 4,
+            ..Default::default()
+        });
+        camera.render_film(&lights, &lights);
+    }
+
+    #[test]
+    fn test_custom_ray_generator() {
+        // Looks straight ahead from the right half of the image only.
+        #[derive(Debug)]
+        struct HalfPanel;
+
+        impl RayGenerator for HalfPanel {
+            fn generate_ray(&self, film: (f64, f64), _lens: (f64, f64)) -> Option<Ray> {
+                (film.0 >= 8.0).then(|| Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, -1.0)))
+            }
+        }
+
+        let camera = Camera::new(CameraArgs {
+            image_width: 16,
+            samples_per_pixel: 2,
+            projection: Projection::Custom(Arc::new(HalfPanel)),
+            ..Default::default()
+        });
+        let pixels = camera.render_pixels(&HittableList::new(), &HittableList::new());
+
+        for (index, color) in pixels.iter().enumerate() {
+            let expected = if index % 16 < 8 {
+                Color::default()
+            } else {
+                Color::new(0.75, 0.85, 1.0)
+            };
+            assert!((*color - expected).length() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    aov::AovPixel,
+    film::{Film, Pixel},
+    vec::{Color, Vec3},
+};
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub path: PathBuf,
+    /// Minimum time between two checkpoints, they are only written between passes.
+    pub interval: Duration,
+    /// Continue from the checkpoint at `path` if there is one.
+    pub resume: bool,
+}
+
+impl Checkpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Duration::from_secs(300),
+            resume: true,
+        }
+    }
+}
+
+/// FNV-1a hash of the debug representation of the scene and camera, stable
+/// across runs and platforms unlike `DefaultHasher`.
+pub fn scene_hash(description: &str) -> u64 {
+    description.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Writes `film` next to `path` first and renames it afterwards, so a crash
+/// while saving keeps the previous checkpoint intact.
+pub fn save(path: &Path, film: &Film, hash: u64) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&hash.to_le_bytes())?;
+    writer.write_all(&film.x.to_le_bytes())?;
+    writer.write_all(&film.y.to_le_bytes())?;
+    writer.write_all(&film.width.to_le_bytes())?;
+    writer.write_all(&film.height.to_le_bytes())?;
+
+    for pixel in &film.pixels {
+        for value in [
+            pixel.sum.x,
+            pixel.sum.y,
+            pixel.sum.z,
+            pixel.weight_sum,
+            pixel.luminance_sum,
+            pixel.luminance_squared_sum,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&pixel.samples.to_le_bytes())?;
+    }
+
+    writer.write_all(&[!film.aovs.is_empty() as u8])?;
+    for aov in &film.aovs {
+        let vectors = [
+            aov.normal_sum,
+            aov.albedo_sum,
+            aov.position_sum,
+            aov.direct_sum,
+            aov.indirect_sum,
+        ];
+        let values = [aov.depth_sum, aov.weight_sum]
+            .into_iter()
+            .chain(vectors.into_iter().flat_map(|v| [v.x, v.y, v.z]));
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for value in [aov.hits, aov.object_id, aov.material_id] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    writer.into_inner()?.sync_all()?;
+    fs::rename(temporary, path)
+}
+
+/// `path` with `.tmp` appended, keeping every extension so that the frames
+/// of an animation do not share one temporary file.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    temporary.into()
+}
+
+/// Reads a checkpoint into the empty `film`, failing with `InvalidData` when
+/// it belongs to a different scene or camera than `hash` describes, covers
+/// another region or lacks the AOVs of `film`.
+pub fn load(path: &Path, hash: u64, mut film: Film) -> io::Result<Film> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC || read_u32(&mut reader)? != VERSION {
+        return Err(invalid_data("not a checkpoint of this version"));
+    }
+    if read_u64(&mut reader)? != hash {
+        return Err(invalid_data(
+            "checkpoint was rendered with another scene or camera",
+        ));
+    }
+
+    // Checked before reading any pixels, the sizes are not trusted.
+    let region = [film.x, film.y, film.width, film.height];
+    for expected in region {
+        if read_u32(&mut reader)? != expected {
+            return Err(invalid_data("checkpoint covers another region"));
+        }
+    }
+
+    for pixel in film.pixels.iter_mut() {
+        let mut values = [0.0; 6];
+        for value in values.iter_mut() {
+            *value = f64::from_bits(read_u64(&mut reader)?);
+        }
+        *pixel = Pixel {
+            sum: Color::new(values[0], values[1], values[2]),
+            weight_sum: values[3],
+            luminance_sum: values[4],
+            luminance_squared_sum: values[5],
+            samples: read_u32(&mut reader)?,
+        };
+    }
+
+    let mut has_aovs = [0];
+    reader.read_exact(&mut has_aovs)?;
+    match (has_aovs[0] != 0, film.aovs.is_empty()) {
+        (true, true) => film = film.with_aovs(),
+        (false, false) => return Err(invalid_data("checkpoint has no AOVs")),
+        _ => {}
+    }
+    for aov in film.aovs.iter_mut() {
+        let mut values = [0.0; 17];
+        for value in values.iter_mut() {
+            *value = f64::from_bits(read_u64(&mut reader)?);
+        }
+        let vector = |i: usize| Vec3::new(values[i], values[i + 1], values[i + 2]);
+        *aov = AovPixel {
+            depth_sum: values[0],
+            weight_sum: values[1],
+            normal_sum: vector(2),
+            albedo_sum: vector(5),
+            position_sum: vector(8),
+            direct_sum: vector(11),
+            indirect_sum: vector(14),
+            hits: read_u32(&mut reader)?,
+            object_id: read_u32(&mut reader)?,
+            material_id: read_u32(&mut reader)?,
+        };
+    }
+
+    Ok(film)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_have_their_own_temporary_file() {
+        let frames = [
+            temporary_path(Path::new("render.ckpt.0001")),
+            temporary_path(Path::new("render.ckpt.0002")),
+        ];
+        assert_eq!(frames[0], Path::new("render.ckpt.0001.tmp"));
+        assert_eq!(frames[1], Path::new("render.ckpt.0002.tmp"));
+        assert_eq!(
+            temporary_path(Path::new("render.tmp")),
+            Path::new("render.tmp.tmp")
+        );
+    }
+}
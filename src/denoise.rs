@@ -0,0 +1,236 @@
+use rayon::prelude::*;
+
+use crate::{
+    film::{Film, Pixel},
+    vec::Color,
+};
+
+/// Joint bilateral filter guided by the albedo and normal AOVs.
+///
+/// The filter runs on the illumination, the pixel color divided by the
+/// albedo, so that texture and material edges survive and only the lighting
+/// noise is smoothed. Neighbors only count if their guides are similar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    /// Half the side of the square of pixels averaged.
+    pub radius: u32,
+    pub sigma_spatial: f64,
+    /// Tolerated difference of the illumination relative to its brightness,
+    /// keeping shadow edges on surfaces with equal guides.
+    pub sigma_color: f64,
+    pub sigma_albedo: f64,
+    /// Tolerated `1 - cos` between the normals.
+    pub sigma_normal: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            radius: 5,
+            sigma_spatial: 3.0,
+            sigma_color: 1.0,
+            sigma_albedo: 0.05,
+            sigma_normal: 0.1,
+        }
+    }
+}
+
+/// Albedo below which a pixel is filtered as is instead of demodulated.
+const MIN_ALBEDO: f64 = 1e-3;
+
+impl Denoiser {
+    /// Copy of `film` with denoised pixel colors, the film must have AOVs.
+    pub fn denoise(&self, film: &Film) -> Film {
+        assert_eq!(film.aovs.len(), film.pixels.len(), "denoising needs AOVs");
+
+        let albedos: Vec<Color> = film.aovs.iter().map(|aov| aov.albedo()).collect();
+        let illumination: Vec<Color> = film
+            .pixels
+            .iter()
+            .zip(&albedos)
+            .map(|(pixel, albedo)| demodulate(pixel.color(), *albedo))
+            .collect();
+
+        let (width, height) = (film.width as i64, film.height as i64);
+        let radius = self.radius as i64;
+
+        let pixels = (0..film.pixels.len())
+            .into_par_iter()
+            .map(|index| {
+                let pixel = film.pixels[index];
+                if pixel.weight_sum == 0.0 {
+                    return pixel;
+                }
+                let (x, y) = (index as i64 % width, index as i64 / width);
+                let center = illumination[index];
+                let normal = film.aovs[index].normal();
+                let scale = center.luminance().abs().max(1e-2);
+
+                let mut sum = Color::default();
+                let mut weight_sum = 0.0;
+                for ny in (y - radius).max(0)..=(y + radius).min(height - 1) {
+                    for nx in (x - radius).max(0)..=(x + radius).min(width - 1) {
+                        let neighbor = (ny * width + nx) as usize;
+                        if film.pixels[neighbor].weight_sum == 0.0 {
+                            continue;
+                        }
+                        let distance_squared = ((nx - x).pow(2) + (ny - y).pow(2)) as f64;
+                        let color_difference =
+                            (illumination[neighbor] - center).luminance() / scale;
+                        let albedo_difference = (albedos[neighbor] - albedos[index]).length();
+                        // Pixels without a hit have no normal to compare.
+                        let neighbor_normal = film.aovs[neighbor].normal();
+                        let normal_difference = if normal.near_zero() || neighbor_normal.near_zero()
+                        {
+                            0.0
+                        } else {
+                            1.0 - neighbor_normal.dot(normal)
+                        };
+
+                        let weight = gaussian(distance_squared.sqrt(), self.sigma_spatial)
+                            * gaussian(color_difference, self.sigma_color)
+                            * gaussian(albedo_difference, self.sigma_albedo)
+                            * gaussian(normal_difference, self.sigma_normal);
+                        sum += illumination[neighbor] * weight;
+                        weight_sum += weight;
+                    }
+                }
+
+                // Guides too strict for any neighbor, even the pixel itself.
+                if weight_sum == 0.0 {
+                    return pixel;
+                }
+                let color = remodulate(sum / weight_sum, albedos[index]);
+                Pixel {
+                    sum: color,
+                    weight_sum: 1.0,
+                    ..pixel
+                }
+            })
+            .collect();
+
+        Film {
+            pixels,
+            ..film.clone()
+        }
+    }
+}
+
+fn gaussian(x: f64, sigma: f64) -> f64 {
+    (-x * x / (2.0 * sigma * sigma)).exp()
+}
+
+fn demodulate(color: Color, albedo: Color) -> Color {
+    let channel = |c: f64, a: f64| if a > MIN_ALBEDO { c / a } else { c };
+    Color::new(
+        channel(color.x, albedo.x),
+        channel(color.y, albedo.y),
+        channel(color.z, albedo.z),
+    )
+}
+
+fn remodulate(illumination: Color, albedo: Color) -> Color {
+    let channel = |c: f64, a: f64| if a > MIN_ALBEDO { c * a } else { c };
+    Color::new(
+        channel(illumination.x, albedo.x),
+        channel(illumination.y, albedo.y),
+        channel(illumination.z, albedo.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        aov::AovPixel,
+        camera::{Camera, CameraArgs},
+        geometries::Quad,
+        hittable::HittableList,
+        materials::Lambertian,
+        vec::Vec3,
+    };
+
+    fn squared_error(film: &Film, reference: &Film, columns: impl Fn(u32) -> bool) -> f64 {
+        let mut sum = 0.0;
+        for (index, (pixel, reference)) in film.pixels.iter().zip(&reference.pixels).enumerate() {
+            if columns(index as u32 % film.width) {
+                sum += (pixel.color() - reference.color()).length_squared();
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn test_background_with_strict_guides_stays_finite() {
+        let mut film = Film::new(8, 8).with_aovs();
+        for pixel in &mut film.pixels {
+            pixel.add_sample(Color::new(0.5, 0.7, 1.0), 1.0);
+        }
+        let denoiser = Denoiser {
+            sigma_color: 1e-3,
+            sigma_albedo: 1e-3,
+            sigma_normal: 0.02,
+            ..Default::default()
+        };
+
+        let denoised = denoiser.denoise(&film);
+        for pixel in &denoised.pixels {
+            let color = pixel.color();
+            assert!(color.x.is_finite() && color.y.is_finite() && color.z.is_finite());
+            assert!(
+                (color - Color::new(0.5, 0.7, 1.0)).length() < 1e-9,
+                "{color:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_denoiser_preserves_albedo_edge() {
+        // A bright and a dark wall meeting in the middle of the image, lit by the sky.
+        let mut world = HittableList::new();
+        for (x, albedo) in [(-2.0, 0.8), (0.0, 0.1)] {
+            world.add(Box::new(Quad::new(
+                Vec3::new(x, -2.0, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 4.0, 0.0),
+                Arc::new(Lambertian::new(Color::new(albedo, albedo, albedo))),
+            )));
+        }
+        let lights = HittableList::new();
+
+        let render = |samples_per_pixel| {
+            Camera::new(CameraArgs {
+                image_width: 32,
+                fov: 60.0,
+                samples_per_pixel,
+                aovs: true,
+                ..Default::default()
+            })
+            .render_film(&world, &lights)
+        };
+        let reference = render(256);
+        let noisy = render(4);
+
+        let denoised = Denoiser::default().denoise(&noisy);
+
+        // Without guides and color term the filter is a plain Gaussian blur.
+        let mut unguided = noisy.clone();
+        unguided.aovs.fill(AovPixel::default());
+        let blurred = Denoiser {
+            sigma_color: f64::INFINITY,
+            ..Default::default()
+        }
+        .denoise(&unguided);
+
+        let all = |_| true;
+        assert!(squared_error(&denoised, &reference, all) < squared_error(&noisy, &reference, all));
+
+        let edge = |column| (14..18).contains(&column);
+        assert!(
+            squared_error(&denoised, &reference, edge)
+                < 0.1 * squared_error(&blurred, &reference, edge)
+        );
+    }
+}
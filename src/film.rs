@@ -0,0 +1,144 @@
+use crate::{aov::AovPixel, scheduler::Tile, vec::Color};
+
+/// Running sums of the samples taken for one pixel. The noise estimate
+/// ignores the filter weights.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pixel {
+    /// Sum of the samples multiplied by their filter weights.
+    pub sum: Color,
+    pub weight_sum: f64,
+    pub luminance_sum: f64,
+    pub luminance_squared_sum: f64,
+    pub samples: u32,
+}
+
+impl Pixel {
+    pub fn add_sample(&mut self, color: Color, weight: f64) {
+        let luminance = color.luminance();
+        self.sum += color * weight;
+        self.weight_sum += weight;
+        self.luminance_sum += luminance;
+        self.luminance_squared_sum += luminance * luminance;
+        self.samples += 1;
+    }
+
+    pub fn color(&self) -> Color {
+        if self.weight_sum == 0.0 {
+            return Color::default();
+        }
+        self.sum / self.weight_sum
+    }
+
+    /// Standard error of the mean luminance, infinite below two samples.
+    pub fn standard_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.samples as f64;
+        let mean = self.luminance_sum / n;
+        let variance = (self.luminance_squared_sum / n - mean * mean).max(0.0) * n / (n - 1.0);
+        (variance / n).sqrt()
+    }
+
+    /// Standard error after gamma encoding, where the image is looked at.
+    pub fn display_error(&self) -> f64 {
+        let mean = self.luminance_sum / self.samples.max(1) as f64;
+        self.standard_error() / (2.0 * mean.max(1e-4).sqrt())
+    }
+}
+
+/// Accumulated samples of an image region, stored row by row. `x` and `y`
+/// locate the region in the full image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Pixel>,
+    /// Either empty or one per pixel.
+    pub aovs: Vec<AovPixel>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::region(0, 0, width, height)
+    }
+
+    pub fn region(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            pixels: vec![Pixel::default(); (width * height) as usize],
+            aovs: Vec::new(),
+        }
+    }
+
+    pub fn with_aovs(self) -> Self {
+        Self {
+            aovs: vec![AovPixel::default(); self.pixels.len()],
+            ..self
+        }
+    }
+
+    pub fn colors(&self) -> Vec<Color> {
+        self.pixels.iter().map(Pixel::color).collect()
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.pixels.iter().map(|pixel| pixel.samples as u64).sum()
+    }
+
+    /// The region placed in an empty film of `width` x `height` pixels.
+    pub fn full_frame(&self, width: u32, height: u32) -> Film {
+        let mut film = Film::new(width, height);
+        if !self.aovs.is_empty() {
+            film = film.with_aovs();
+        }
+        let tile = Tile {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        };
+        film.set_tile_pixels(&tile, &self.pixels, &self.aovs);
+        film
+    }
+}
+
+impl Film {
+    /// Copies of the pixels and AOVs inside `tile`, row by row.
+    pub fn tile_pixels(&self, tile: &Tile) -> (Vec<Pixel>, Vec<AovPixel>) {
+        (
+            self.copy_tile(&self.pixels, tile),
+            self.copy_tile(&self.aovs, tile),
+        )
+    }
+
+    pub fn set_tile_pixels(&mut self, tile: &Tile, pixels: &[Pixel], aovs: &[AovPixel]) {
+        let width = self.width;
+        paste_tile(&mut self.pixels, width, tile, pixels);
+        paste_tile(&mut self.aovs, width, tile, aovs);
+    }
+
+    fn copy_tile<T: Copy>(&self, values: &[T], tile: &Tile) -> Vec<T> {
+        if values.is_empty() {
+            return Vec::new();
+        }
+        (tile.y..tile.y + tile.height)
+            .flat_map(|y| {
+                let start = (y * self.width + tile.x) as usize;
+                values[start..start + tile.width as usize].iter().copied()
+            })
+            .collect()
+    }
+}
+
+fn paste_tile<T: Copy>(values: &mut [T], width: u32, tile: &Tile, tile_values: &[T]) {
+    for (row, tile_row) in tile_values.chunks(tile.width as usize).enumerate() {
+        let start = ((tile.y + row as u32) * width + tile.x) as usize;
+        values[start..start + tile_row.len()].copy_from_slice(tile_row);
+    }
+}
@@ -0,0 +1,248 @@
+use std::f64::consts::PI;
+
+/// Reconstruction filter weighting the samples of a pixel by their offset
+/// from its center. All filters are separable, `radius` is in pixels and
+/// must be positive.
+///
+/// Instead of splatting every sample into all pixels within the radius, the
+/// samples of a pixel are spread over the radius with a density following
+/// the filter, as pbrt-v4 does. Both converge to the same image, but every
+/// sample stays in its own pixel: threads never write to the same pixel,
+/// adaptive sampling and checkpoints see independent pixels, and the noise
+/// of neighbouring pixels is not correlated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box {
+        radius: f64,
+    },
+    Tent {
+        radius: f64,
+    },
+    Gaussian {
+        radius: f64,
+        sigma: f64,
+    },
+    /// Mitchell-Netravali cubic, `b = c = 1/3` is the recommended choice.
+    Mitchell {
+        radius: f64,
+        b: f64,
+        c: f64,
+    },
+    /// Sinc windowed by a wider sinc with `tau` lobes.
+    Lanczos {
+        radius: f64,
+        tau: f64,
+    },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let radius = self.radius();
+        if x.abs() > radius {
+            return 0.0;
+        }
+
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { .. } => radius - x.abs(),
+            Filter::Gaussian { sigma, .. } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { b, c, .. } => mitchell(2.0 * x / radius, b, c),
+            Filter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    if x <= 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b))
+            / 6.0
+    } else if x <= 2.0 {
+        ((-b - 6.0 * c) * x.powi(3)
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+/// Offset of a camera sample from the pixel center and the weight it is
+/// accumulated with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterSample {
+    pub offset: (f64, f64),
+    pub weight: f64,
+}
+
+/// Draws sample offsets proportional to the magnitude of a filter from a
+/// tabulated distribution per axis. The weights `f / pdf` then only vary
+/// with the sign of the filter and the tabulation error.
+#[derive(Debug, Clone)]
+pub(crate) struct FilterSampler {
+    filter: Filter,
+    /// Magnitude of the filter in each cell of `[-radius, radius]`.
+    cells: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+const CELLS_PER_PIXEL: f64 = 32.0;
+
+impl FilterSampler {
+    pub fn new(filter: Filter) -> Self {
+        let radius = filter.radius();
+        assert!(
+            radius > 0.0 && radius.is_finite(),
+            "filter radius must be positive, got {radius}"
+        );
+        let count = (2.0 * radius * CELLS_PER_PIXEL).ceil().max(1.0) as usize;
+        let cell_width = 2.0 * radius / count as f64;
+
+        // The largest magnitude at the ends and the middle of a cell, so that
+        // cells around zero crossings keep a non-zero probability.
+        let cells: Vec<f64> = (0..count)
+            .map(|i| {
+                let start = -radius + i as f64 * cell_width;
+                [0.0, 0.5, 1.0]
+                    .map(|t| filter.evaluate_1d(start + t * cell_width).abs())
+                    .into_iter()
+                    .fold(0.0, f64::max)
+            })
+            .collect();
+
+        let mut cdf = vec![0.0];
+        for value in &cells {
+            cdf.push(cdf.last().unwrap() + value * cell_width);
+        }
+        let integral = *cdf.last().unwrap();
+        assert!(integral > 0.0, "{filter:?} is zero everywhere");
+        cdf.iter_mut().for_each(|value| *value /= integral);
+
+        Self {
+            filter,
+            cells,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn sample(&self, u: (f64, f64)) -> FilterSample {
+        let (x, pdf_x) = self.sample_1d(u.0);
+        let (y, pdf_y) = self.sample_1d(u.1);
+        FilterSample {
+            offset: (x, y),
+            weight: self.filter.evaluate(x, y) / (pdf_x * pdf_y),
+        }
+    }
+
+    fn sample_1d(&self, u: f64) -> (f64, f64) {
+        let radius = self.filter.radius();
+        let cell_width = 2.0 * radius / self.cells.len() as f64;
+        let cell = (self.cdf.partition_point(|&value| value <= u) - 1).min(self.cells.len() - 1);
+
+        let probability = self.cdf[cell + 1] - self.cdf[cell];
+        let t = ((u - self.cdf[cell]) / probability).clamp(0.0, 1.0);
+        let x = -radius + (cell as f64 + t) * cell_width;
+
+        (x, self.cells[cell] / self.integral)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_box_filter_is_uniform() {
+        let sampler = FilterSampler::new(Filter::default());
+        for u in [(0.0, 0.0), (0.25, 0.7), (0.999, 0.5)] {
+            let sample = sampler.sample(u);
+            assert!((sample.offset.0 - (u.0 - 0.5)).abs() < 1e-12);
+            assert!((sample.offset.1 - (u.1 - 0.5)).abs() < 1e-12);
+            assert!((sample.weight - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "filter radius must be positive")]
+    fn test_zero_radius_is_rejected() {
+        FilterSampler::new(Filter::Tent { radius: 0.0 });
+    }
+
+    #[test]
+    fn test_weights_estimate_filter_integral() {
+        let filters = [
+            Filter::Tent { radius: 1.0 },
+            Filter::Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            },
+            Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+            Filter::Lanczos {
+                radius: 3.0,
+                tau: 3.0,
+            },
+        ];
+
+        for filter in filters {
+            let sampler = FilterSampler::new(filter);
+            let n = 256;
+            let mut estimate = 0.0;
+            let mut integral = 0.0;
+            let step = 2.0 * filter.radius() / n as f64;
+            for i in 0..n {
+                for j in 0..n {
+                    let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                    estimate += sampler.sample(u).weight / (n * n) as f64;
+
+                    let x = -filter.radius() + (i as f64 + 0.5) * step;
+                    let y = -filter.radius() + (j as f64 + 0.5) * step;
+                    integral += filter.evaluate(x, y) * step * step;
+                }
+            }
+
+            assert!(
+                (estimate - integral).abs() < 0.01 * integral,
+                "{filter:?}: {estimate} != {integral}"
+            );
+        }
+    }
+}
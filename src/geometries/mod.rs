@@ -1,5 +1,7 @@
 mod plane;
+mod quad;
 mod sphere;
 
 pub use plane::Plane;
+pub use quad::Quad;
 pub use sphere::Sphere;
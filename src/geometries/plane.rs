@@ -45,4 +45,8 @@ impl Hittable for Plane {
             self.material.clone(),
         ))
     }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        Some(&self.material)
+    }
 }
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use crate::hittable::{HitRecord, Hittable};
+
+use crate::interval::Interval;
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+#[derive(Debug, Clone)]
+pub struct Quad {
+    corner: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit_vector();
+
+        Self {
+            corner,
+            u,
+            v,
+            w: n / n.length_squared(),
+            normal,
+            d: normal.dot(corner),
+            area: n.length(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+        let denom = self.normal.dot(ray.dir);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(ray.orig)) / denom;
+        if !range.surrounds(t) {
+            return None;
+        }
+
+        let p = ray.at(t);
+        let planar_hit = p - self.corner;
+        let alpha = self.w.dot(planar_hit.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar_hit));
+
+        let unit = Interval::new(0.0, 1.0);
+        if !unit.contains(alpha) || !unit.contains(beta) {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            p,
+            self.normal,
+            t,
+            ray,
+            self.material.clone(),
+        ))
+    }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        Some(&self.material)
+    }
+
+    fn is_sampleable(&self) -> bool {
+        true
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f64 {
+        let Some(hit_record) = self.hit(
+            &Ray::new(origin, direction),
+            Interval::new(0.001, f64::INFINITY),
+        ) else {
+            return 0.0;
+        };
+
+        let distance_squared = hit_record.t.powi(2) * direction.length_squared();
+        let cosine = (direction.dot(self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Vec3, u: (f64, f64)) -> Vec3 {
+        let p = self.corner + u.0 * self.u + u.1 * self.v;
+        p - origin
+    }
+}
@@ -1,11 +1,11 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::hittable::{HitRecord, Hittable};
 
 use crate::interval::Interval;
 use crate::materials::Material;
 use crate::ray::Ray;
-use crate::vec::Vec3;
+use crate::vec::{Onb, Vec3};
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
@@ -52,4 +52,44 @@ impl Hittable for Sphere {
 
         Some(HitRecord::new(p, normal, root, ray, self.material.clone()))
     }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        Some(&self.material)
+    }
+
+    fn is_sampleable(&self) -> bool {
+        true
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f64 {
+        if self
+            .hit(
+                &Ray::new(origin, direction),
+                Interval::new(0.001, f64::INFINITY),
+            )
+            .is_none()
+        {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center - origin).length_squared();
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared)
+            .max(0.0)
+            .sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Vec3, u: (f64, f64)) -> Vec3 {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+
+        let cos_theta_max = (1.0 - self.radius.powi(2) / distance_squared)
+            .max(0.0)
+            .sqrt();
+        let local = Vec3::sample_uniform_cone(u, cos_theta_max);
+
+        Onb::new(direction).transform(local)
+    }
 }
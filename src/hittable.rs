@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{fmt::Debug, sync::Arc};
 
 use crate::{interval::Interval, materials::Material, ray::Ray, vec::Vec3};
 
@@ -9,6 +9,11 @@ pub struct HitRecord {
     pub t: f64,
     pub is_front_face: bool,
     pub material: Arc<dyn Material>,
+    /// Index of the object in the `HittableList` that was hit.
+    pub object_id: usize,
+    /// Index of the first object in the `HittableList` sharing the material
+    /// of the one that was hit.
+    pub material_id: usize,
 }
 
 impl HitRecord {
@@ -26,6 +31,8 @@ impl HitRecord {
             t,
             is_front_face,
             material,
+            object_id: 0,
+            material_id: 0,
         }
     }
 
@@ -40,8 +47,17 @@ impl HitRecord {
     }
 }
 
+/// Object of a `HittableList`, whose debug output is part of the scene hash
+/// that checkpoints are matched with.
+pub trait SceneObject: Hittable + Debug + Sync + Send {}
+
+impl<T: Hittable + Debug + Sync + Send> SceneObject for T {}
+
+#[derive(Debug)]
 pub struct HittableList {
-    objects: Vec<Box<dyn Hittable + Sync + Send>>,
+    objects: Vec<Box<dyn SceneObject>>,
+    /// `HitRecord::material_id` of every object.
+    material_ids: Vec<usize>,
 }
 
 impl Default for HittableList {
@@ -54,28 +70,94 @@ impl HittableList {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
+            material_ids: Vec::new(),
         }
     }
 
-    pub fn add(&mut self, object: Box<dyn Hittable + Sync + Send>) {
+    /// Objects with the same `Arc` of a material share its ID, materials
+    /// with equal parameters do not.
+    pub fn add(&mut self, object: Box<dyn SceneObject>) {
+        let material_id = object
+            .material()
+            .and_then(|material| {
+                self.objects.iter().position(|other| {
+                    other
+                        .material()
+                        .is_some_and(|other| Arc::ptr_eq(other, material))
+                })
+            })
+            .unwrap_or(self.objects.len());
+        self.material_ids.push(material_id);
         self.objects.push(object);
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Whether all objects can be sampled as lights.
+    pub fn is_sampleable(&self) -> bool {
+        self.objects.iter().all(|object| object.is_sampleable())
+    }
+
     pub fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
         let mut closest_so_far = range.max;
         let mut hit_record = None;
 
-        for object in self.objects.iter() {
+        for (index, object) in self.objects.iter().enumerate() {
             if let Some(record) = object.hit(ray, Interval::new(range.min, closest_so_far)) {
                 closest_so_far = record.t;
-                hit_record = Some(record);
+                hit_record = Some(HitRecord {
+                    object_id: index,
+                    material_id: self.material_ids[index],
+                    ..record
+                });
             }
         }
 
         hit_record
     }
+
+    pub fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / self.objects.len() as f64
+    }
+
+    /// Picks one of the objects with `u_object` and samples it with `u`.
+    pub fn random(&self, origin: Vec3, u_object: f64, u: (f64, f64)) -> Vec3 {
+        let index = ((u_object * self.objects.len() as f64) as usize).min(self.objects.len() - 1);
+        self.objects[index].random(origin, u)
+    }
 }
 
 pub trait Hittable {
     fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord>;
+
+    /// The material of the whole object, if it has a single one.
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        None
+    }
+
+    /// Whether the object implements `pdf_value` and `random`, which the
+    /// objects passed as lights must.
+    fn is_sampleable(&self) -> bool {
+        false
+    }
+
+    /// Solid angle density of `random` sampling `direction` from `origin`.
+    fn pdf_value(&self, _origin: Vec3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Direction from `origin` towards a point on the object picked with `u`.
+    fn random(&self, _origin: Vec3, _u: (f64, f64)) -> Vec3 {
+        unimplemented!("the object cannot be sampled as a light")
+    }
 }
@@ -1,7 +1,17 @@
+pub mod animation;
+pub mod aov;
 pub mod camera;
+pub mod checkpoint;
+pub mod denoise;
+pub mod film;
+pub mod filter;
 pub mod geometries;
 pub mod hittable;
 pub mod interval;
 pub mod materials;
+pub mod projections;
 pub mod ray;
+pub mod samplers;
+pub mod scheduler;
+pub mod tone_mapping;
 pub mod vec;
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use raytracing::{
     camera::{Camera, CameraArgs},
     geometries::Sphere,
@@ -22,6 +23,7 @@ fn main() {
     });
 
     let mut world = HittableList::new();
+    let mut rng = StdRng::seed_from_u64(0);
 
     let ground_material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
     world.add(Box::new(Sphere::new(
@@ -32,20 +34,20 @@ fn main() {
 
     for a in -11..=11 {
         for b in -11..=11 {
-            let choose_material = rand::random::<f64>();
+            let choose_material = rng.gen::<f64>();
             let center = Vec3::new(
-                a as f64 + 0.9 * rand::random::<f64>(),
+                a as f64 + 0.9 * rng.gen::<f64>(),
                 0.2,
-                b as f64 + 0.9 * rand::random::<f64>(),
+                b as f64 + 0.9 * rng.gen::<f64>(),
             );
 
             if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 let sphere_material: Arc<dyn Material> = if choose_material < 0.8 {
-                    let albedo = Vec3::random() * Vec3::random();
+                    let albedo = Vec3::random_with(&mut rng) * Vec3::random_with(&mut rng);
                     Arc::new(Lambertian::new(albedo))
                 } else if choose_material < 0.95 {
-                    let albedo = Vec3::random_range(0.5..1.0);
-                    let fuzz = rand::random::<f64>() * 0.5;
+                    let albedo = Vec3::random_range_with(&mut rng, 0.5..1.0);
+                    let fuzz = rng.gen::<f64>() * 0.5;
                     Arc::new(Metal::new(albedo, fuzz))
                 } else {
                     Arc::new(Dielectric::new(1.5))
@@ -77,5 +79,5 @@ fn main() {
         material3,
     )));
 
-    camera.render(&world);
+    camera.render(&world, &HittableList::new());
 }
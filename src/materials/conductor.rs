@@ -0,0 +1,377 @@
+use super::{BsdfSample, Material};
+use crate::{
+    hittable::HitRecord,
+    vec::{Color, Onb, Vec3},
+};
+use std::{f64::consts::PI, fmt::Debug};
+
+/// Reflectance of a metal by the angle between the light and the microfacet normal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fresnel {
+    /// Schlick's approximation from the color at normal incidence.
+    Schlick(Color),
+    /// Exact Fresnel equations for the complex index of refraction `eta + ik`
+    /// of each channel, as measured for real metals.
+    Complex { eta: Color, k: Color },
+}
+
+impl Fresnel {
+    fn evaluate(&self, cosine: f64) -> Color {
+        match *self {
+            Fresnel::Schlick(f0) => f0 + (Color::new(1.0, 1.0, 1.0) - f0) * (1.0 - cosine).powi(5),
+            Fresnel::Complex { eta, k } => Color::new(
+                fresnel_complex(cosine, eta.x, k.x),
+                fresnel_complex(cosine, eta.y, k.y),
+                fresnel_complex(cosine, eta.z, k.z),
+            ),
+        }
+    }
+}
+
+/// Unpolarized reflectance of a conductor in air.
+fn fresnel_complex(cosine: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cosine * cosine;
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2.0 * cosine * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rs + rp)
+}
+
+/// Rough metal made of microscopic mirrors with GGX distributed normals,
+/// shadowing and masking each other following Smith's height correlated
+/// model. Light scattered more than once between the facets is lost, which
+/// darkens very rough surfaces slightly.
+#[derive(Debug)]
+pub struct Conductor {
+    fresnel: Fresnel,
+    /// GGX widths along the two tangents of the basis around the normal.
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+/// Widths below which the surface is treated as a perfect mirror.
+const SMOOTH_ALPHA: f64 = 1e-3;
+/// Smallest width of a rough surface, so that a smooth tangent of an
+/// anisotropic one does not divide by zero.
+const MIN_ALPHA: f64 = 1e-4;
+
+impl Conductor {
+    /// `roughness` is the perceptual roughness in [0, 1], its square being
+    /// the GGX width.
+    pub fn new(fresnel: Fresnel, roughness: f64) -> Self {
+        Self::anisotropic(fresnel, roughness, roughness)
+    }
+
+    /// Stretched highlights with separate roughnesses along two tangents,
+    /// which follow the basis built around the normal and are not aligned
+    /// with any texture direction.
+    pub fn anisotropic(fresnel: Fresnel, roughness_x: f64, roughness_y: f64) -> Self {
+        let mut alpha_x = roughness_x.clamp(0.0, 1.0).powi(2);
+        let mut alpha_y = roughness_y.clamp(0.0, 1.0).powi(2);
+        if alpha_x.max(alpha_y) >= SMOOTH_ALPHA {
+            alpha_x = alpha_x.max(MIN_ALPHA);
+            alpha_y = alpha_y.max(MIN_ALPHA);
+        }
+        Self {
+            fresnel,
+            alpha_x,
+            alpha_y,
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(
+            Fresnel::Complex {
+                eta: Color::new(0.143, 0.374, 1.442),
+                k: Color::new(3.983, 2.385, 1.603),
+            },
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(
+            Fresnel::Complex {
+                eta: Color::new(0.200, 0.924, 1.102),
+                k: Color::new(3.912, 2.452, 2.142),
+            },
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::new(
+            Fresnel::Complex {
+                eta: Color::new(1.657, 0.880, 0.521),
+                k: Color::new(9.224, 6.270, 4.837),
+            },
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(
+            Fresnel::Complex {
+                eta: Color::new(0.155, 0.117, 0.138),
+                k: Color::new(4.828, 3.122, 2.147),
+            },
+            roughness,
+        )
+    }
+
+    fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    /// Density of microfacet normals, `m` in the local basis.
+    fn distribution(&self, m: Vec3) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let (ax, ay) = (self.alpha_x, self.alpha_y);
+        let e = (m.x * m.x / (ax * ax) + m.y * m.y / (ay * ay)) / (m.z * m.z);
+        1.0 / (PI * ax * ay * m.z.powi(4) * (1.0 + e).powi(2))
+    }
+
+    /// Smith's auxiliary function, the masked facet area per visible area.
+    fn lambda(&self, w: Vec3) -> f64 {
+        let alpha2_tan2 = (w.x * w.x * self.alpha_x * self.alpha_x
+            + w.y * w.y * self.alpha_y * self.alpha_y)
+            / (w.z * w.z);
+        if !alpha2_tan2.is_finite() {
+            return 0.0;
+        }
+        0.5 * ((1.0 + alpha2_tan2).sqrt() - 1.0)
+    }
+
+    fn masking(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    fn shadowing_masking(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the normals of the facets visible from `w`.
+    fn visible_distribution(&self, w: Vec3, m: Vec3) -> f64 {
+        self.masking(w) / w.z * self.distribution(m) * w.dot(m).max(0.0)
+    }
+
+    /// Samples the visible normals by projecting a point on the disk onto the
+    /// hemisphere stretched to unit roughness (Heitz, "Sampling the GGX
+    /// Distribution of Visible Normals").
+    fn sample_visible_normal(&self, w: Vec3, u: (f64, f64)) -> Vec3 {
+        let wh = Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).unit_vector();
+        let t1 = if wh.z < 0.99999 {
+            Vec3::new(0.0, 0.0, 1.0).cross(wh).unit_vector()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = wh.cross(t1);
+
+        // Only the part of the disk in front of the projected hemisphere's
+        // horizon is seen from `w`.
+        let p = Vec3::sample_concentric_disk(u);
+        let h = (1.0 - p.x * p.x).sqrt();
+        let s = 0.5 * (1.0 + wh.z);
+        let py = (1.0 - s) * h + s * p.y;
+        let pz = (1.0 - p.x * p.x - py * py).max(0.0).sqrt();
+        let nh = p.x * t1 + py * t2 + pz * wh;
+
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit_vector()
+    }
+}
+
+/// Coordinates of `v` in `basis`.
+fn to_local(basis: &Onb, v: Vec3) -> Vec3 {
+    Vec3::new(v.dot(basis.u), v.dot(basis.v), v.dot(basis.w))
+}
+
+impl Material for Conductor {
+    fn eval(&self, hit_record: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        if self.is_smooth() {
+            return Color::default();
+        }
+        let basis = Onb::new(hit_record.normal);
+        let (wo, wi) = (to_local(&basis, wo), to_local(&basis, wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::default();
+        }
+
+        let m = (wo + wi).unit_vector();
+        self.fresnel.evaluate(wo.dot(m).abs())
+            * (self.distribution(m) * self.shadowing_masking(wo, wi) / (4.0 * wo.z * wi.z))
+    }
+
+    fn pdf(&self, hit_record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if self.is_smooth() {
+            return 0.0;
+        }
+        let basis = Onb::new(hit_record.normal);
+        let (wo, wi) = (to_local(&basis, wo), to_local(&basis, wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        // Reflection halves the angles, the Jacobian from normals to directions.
+        let m = (wo + wi).unit_vector();
+        self.visible_distribution(wo, m) / (4.0 * wo.dot(m))
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vec3,
+        _uc: f64,
+        u: (f64, f64),
+    ) -> Option<BsdfSample> {
+        let basis = Onb::new(hit_record.normal);
+        let wo_local = to_local(&basis, wo);
+        if wo_local.z <= 0.0 {
+            return None;
+        }
+
+        if self.is_smooth() {
+            let direction = (-wo).reflect(hit_record.normal);
+            return Some(BsdfSample {
+                direction,
+                f: self.fresnel.evaluate(wo_local.z) / wo_local.z,
+                pdf: 1.0,
+                is_specular: true,
+            });
+        }
+
+        let m = self.sample_visible_normal(wo_local, u);
+        let wi = (-wo_local).reflect(m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        let direction = basis.transform(wi);
+        Some(BsdfSample {
+            direction,
+            f: self.eval(hit_record, wo, direction),
+            pdf: self.pdf(hit_record, wo, direction),
+            is_specular: false,
+        })
+    }
+
+    fn is_specular(&self) -> bool {
+        self.is_smooth()
+    }
+
+    fn albedo(&self) -> Color {
+        self.fresnel.evaluate(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ray::Ray;
+
+    #[test]
+    fn test_sampling_matches_evaluation() {
+        let white = Fresnel::Schlick(Color::new(1.0, 1.0, 1.0));
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+
+        // Rough surfaces lose the light reflected below the horizon, which
+        // would be reflected again by other facets.
+        for (roughness_x, roughness_y, min_albedo) in
+            [(0.2, 0.2, 0.99), (0.7, 0.7, 0.65), (0.3, 0.8, 0.65)]
+        {
+            let material = Arc::new(Conductor::anisotropic(white, roughness_x, roughness_y));
+            let ray = Ray::new(wo, -wo);
+            let hit_record = HitRecord::new(
+                Vec3::default(),
+                Vec3::new(0.0, 0.0, 1.0),
+                1.0,
+                &ray,
+                material.clone(),
+            );
+
+            // Estimate of the directional albedo from the samples.
+            let n = 256;
+            let mut estimate = 0.0;
+            for i in 0..n {
+                for j in 0..n {
+                    let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                    let Some(sample) = material.sample(&hit_record, wo, 0.0, u) else {
+                        continue;
+                    };
+                    let direction = sample.direction;
+                    assert!((sample.pdf - material.pdf(&hit_record, wo, direction)).abs() < 1e-9);
+                    estimate += sample.weight(hit_record.normal).x / (n * n) as f64;
+                }
+            }
+
+            // The same integral by quadrature over the hemisphere.
+            let mut integral = 0.0;
+            let mut pdf_integral = 0.0;
+            let steps = 512;
+            for i in 0..steps {
+                let cos_theta = (i as f64 + 0.5) / steps as f64;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                for j in 0..steps {
+                    let phi = 2.0 * PI * (j as f64 + 0.5) / steps as f64;
+                    let wi = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+                    let solid_angle = 2.0 * PI / (steps * steps) as f64;
+                    integral += material.eval(&hit_record, wo, wi).x * cos_theta * solid_angle;
+                    pdf_integral += material.pdf(&hit_record, wo, wi) * solid_angle;
+                }
+            }
+
+            assert!(
+                (estimate - integral).abs() < 0.01,
+                "{roughness_x} {roughness_y}: {estimate} != {integral}"
+            );
+            assert!(integral <= 1.0 && integral > min_albedo);
+            assert!(pdf_integral <= 1.0 + 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_one_smooth_tangent() {
+        let white = Fresnel::Schlick(Color::new(1.0, 1.0, 1.0));
+        let material = Arc::new(Conductor::anisotropic(white, 0.0, 0.5));
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let ray = Ray::new(wo, -wo);
+        let hit_record = HitRecord::new(
+            Vec3::default(),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            &ray,
+            material.clone(),
+        );
+        assert!(!material.is_specular());
+
+        let n = 64;
+        let mut estimate = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                let Some(sample) = material.sample(&hit_record, wo, 0.0, u) else {
+                    continue;
+                };
+                let f = material.eval(&hit_record, wo, sample.direction);
+                assert!(f.x.is_finite() && sample.pdf.is_finite() && sample.pdf > 0.0);
+                assert!(
+                    (sample.pdf - material.pdf(&hit_record, wo, sample.direction)).abs() < 1e-9
+                );
+                estimate += sample.weight(hit_record.normal).x / (n * n) as f64;
+            }
+        }
+        // The facets only tilt along one tangent, hardly losing any light.
+        assert!(estimate <= 1.0 + 1e-9 && estimate > 0.95, "{estimate}");
+    }
+}
@@ -1,8 +1,10 @@
-use crate::{hittable::HitRecord, ray::Ray, vec::Vec3};
-use rand::Rng;
+use crate::{
+    hittable::HitRecord,
+    vec::{Color, Vec3},
+};
 use std::fmt::Debug;
 
-use super::Material;
+use super::{BsdfSample, Material};
 
 #[derive(Debug)]
 pub struct Dielectric {
@@ -21,30 +23,49 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let attenuation = Vec3::new(1.0, 1.0, 1.0);
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vec3,
+        uc: f64,
+        _u: (f64, f64),
+    ) -> Option<BsdfSample> {
+        let attenuation = Color::new(1.0, 1.0, 1.0);
         let refraction_ratio = if hit_record.is_front_face {
             1.0 / self.refractive_index
         } else {
             self.refractive_index
         };
 
-        let unit_direction = ray.dir.unit_vector();
+        let unit_direction = -wo;
 
-        let cos_theta = (-unit_direction).dot(hit_record.normal).min(1.0);
+        let cos_theta = wo.dot(hit_record.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_reflect = refraction_ratio * sin_theta > 1.0;
 
-        let mut rng = rand::thread_rng();
-        let new_direction = if cannot_reflect
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > rng.gen_range(0.0..1.0)
-        {
-            ray.dir.reflect(hit_record.normal)
-        } else {
-            unit_direction.refract(hit_record.normal, refraction_ratio)
-        };
+        let direction =
+            if cannot_reflect || Dielectric::reflectance(cos_theta, refraction_ratio) > uc {
+                unit_direction.reflect(hit_record.normal)
+            } else {
+                unit_direction
+                    .refract(hit_record.normal, refraction_ratio)
+                    .unit_vector()
+            };
+
+        Some(BsdfSample {
+            direction,
+            f: attenuation / direction.dot(hit_record.normal).abs(),
+            pdf: 1.0,
+            is_specular: true,
+        })
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
 
-        Some((attenuation, Ray::new(hit_record.p, new_direction)))
+    fn albedo(&self) -> Color {
+        Color::new(1.0, 1.0, 1.0)
     }
 }
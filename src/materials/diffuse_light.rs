@@ -0,0 +1,24 @@
+use super::Material;
+use crate::{hittable::HitRecord, ray::Ray, vec::Color};
+use std::fmt::Debug;
+
+#[derive(Debug)]
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, _ray: &Ray, hit_record: &HitRecord) -> Color {
+        if hit_record.is_front_face {
+            self.emit
+        } else {
+            Color::default()
+        }
+    }
+}
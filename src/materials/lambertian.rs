@@ -1,6 +1,9 @@
-use super::Material;
-use crate::{hittable::HitRecord, ray::Ray, vec::Vec3};
-use std::fmt::Debug;
+use super::{BsdfSample, Material};
+use crate::{
+    hittable::HitRecord,
+    vec::{Color, Onb, Vec3},
+};
+use std::{f64::consts::PI, fmt::Debug};
 
 #[derive(Debug)]
 pub struct Lambertian {
@@ -14,13 +17,41 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let mut scatter_direction = hit_record.normal + Vec3::random_in_unit_sphere();
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
+    fn eval(&self, hit_record: &HitRecord, _wo: Vec3, wi: Vec3) -> Color {
+        if hit_record.normal.dot(wi) <= 0.0 {
+            return Color::default();
         }
-        let scattered = Ray::new(hit_record.p, scatter_direction);
-        let attenuation = self.albedo;
-        Some((attenuation, scattered))
+        self.albedo / PI
+    }
+
+    fn pdf(&self, hit_record: &HitRecord, _wo: Vec3, wi: Vec3) -> f64 {
+        hit_record.normal.dot(wi).max(0.0) / PI
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vec3,
+        _uc: f64,
+        u: (f64, f64),
+    ) -> Option<BsdfSample> {
+        let local = Vec3::sample_cosine_hemisphere(u);
+        let direction = Onb::new(hit_record.normal).transform(local);
+
+        let pdf = self.pdf(hit_record, wo, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction,
+            f: self.eval(hit_record, wo, direction),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    fn albedo(&self) -> Color {
+        self.albedo
     }
 }
@@ -1,7 +1,10 @@
-use crate::{hittable::HitRecord, ray::Ray, vec::Vec3};
-use std::fmt::Debug;
+use crate::{
+    hittable::HitRecord,
+    vec::{Color, Vec3},
+};
+use std::{f64::consts::PI, fmt::Debug};
 
-use super::Material;
+use super::{BsdfSample, Material};
 
 #[derive(Debug)]
 pub struct Metal {
@@ -19,15 +22,78 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let mut reflected_dir = ray.dir.reflect(hit_record.normal).unit_vector();
-        reflected_dir += Vec3::random_in_unit_sphere() * self.fuzz;
-        let ray_reflected = Ray::new(hit_record.p, reflected_dir);
-
-        if ray_reflected.dir.dot(hit_record.normal) > 0.0 {
-            Some((self.albedo, ray_reflected))
-        } else {
-            None
+    fn eval(&self, hit_record: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let cosine = wi.dot(hit_record.normal);
+        if self.fuzz <= 0.0 || cosine <= 0.0 {
+            return Color::default();
         }
+        // The fuzzed reflection is kept with weight `albedo`, so f * cos = albedo * pdf.
+        self.albedo * self.pdf(hit_record, wo, wi) / cosine
+    }
+
+    fn pdf(&self, hit_record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if self.fuzz <= 0.0 || wi.dot(hit_record.normal) <= 0.0 {
+            return 0.0;
+        }
+
+        // `sample` picks a uniform point on the fuzz sphere around the mirror
+        // direction. Intersect the sphere with `wi` and convert the area
+        // density at both intersections into a solid angle density.
+        let reflected_dir = (-wo).reflect(hit_record.normal);
+        let b = wi.dot(reflected_dir);
+        let discriminant = b * b - 1.0 + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let distances_squared: f64 = [b - sqrtd, b + sqrtd]
+            .into_iter()
+            .filter(|t| *t > 0.0)
+            .map(|t| t * t)
+            .sum();
+
+        distances_squared / (4.0 * PI * self.fuzz * sqrtd)
+    }
+
+    fn sample(
+        &self,
+        hit_record: &HitRecord,
+        wo: Vec3,
+        _uc: f64,
+        u: (f64, f64),
+    ) -> Option<BsdfSample> {
+        let mut reflected_dir = (-wo).reflect(hit_record.normal);
+        reflected_dir += Vec3::sample_uniform_sphere(u) * self.fuzz;
+        let direction = reflected_dir.unit_vector();
+
+        let cosine = direction.dot(hit_record.normal);
+        if cosine <= 0.0 {
+            return None;
+        }
+
+        if self.fuzz <= 0.0 {
+            return Some(BsdfSample {
+                direction,
+                f: self.albedo / cosine,
+                pdf: 1.0,
+                is_specular: true,
+            });
+        }
+
+        Some(BsdfSample {
+            direction,
+            f: self.eval(hit_record, wo, direction),
+            pdf: self.pdf(hit_record, wo, direction),
+            is_specular: false,
+        })
+    }
+
+    fn is_specular(&self) -> bool {
+        self.fuzz <= 0.0
+    }
+
+    fn albedo(&self) -> Color {
+        self.albedo
     }
 }
@@ -1,14 +1,78 @@
-use crate::{hittable::HitRecord, ray::Ray, vec::Vec3};
+use crate::{
+    hittable::HitRecord,
+    ray::Ray,
+    vec::{Color, Vec3},
+};
 use std::fmt::Debug;
 
+mod conductor;
 mod dielectric;
+mod diffuse_light;
 mod lambertian;
 mod metal;
 
+pub use conductor::{Conductor, Fresnel};
 pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
 
+/// A direction picked by `Material::sample`.
+///
+/// For specular samples `f` and `pdf` are relative to a delta distribution,
+/// only `f * |cos| / pdf` is meaningful.
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    pub direction: Vec3,
+    pub f: Color,
+    pub pdf: f64,
+    pub is_specular: bool,
+}
+
+impl BsdfSample {
+    /// Throughput `f * |cos| / pdf` of a path continuing in this direction.
+    pub fn weight(&self, normal: Vec3) -> Color {
+        self.f * self.direction.dot(normal).abs() / self.pdf
+    }
+}
+
+/// Scattering at a surface. `wo` points back along the incoming ray and `wi`
+/// away from the surface along the scattered one, both are unit vectors.
 pub trait Material: Send + Sync + Debug {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Vec3, Ray)>;
+    /// BSDF value f(wi, wo), zero for specular lobes.
+    fn eval(&self, _hit_record: &HitRecord, _wo: Vec3, _wi: Vec3) -> Color {
+        Color::default()
+    }
+
+    /// Solid angle density with which `sample` picks `wi`, zero for specular lobes.
+    fn pdf(&self, _hit_record: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Picks a scattering direction from the sample values `uc` and `u`,
+    /// `None` when the path is absorbed.
+    fn sample(
+        &self,
+        _hit_record: &HitRecord,
+        _wo: Vec3,
+        _uc: f64,
+        _u: (f64, f64),
+    ) -> Option<BsdfSample> {
+        None
+    }
+
+    /// Whether all scattering happens into discrete directions, in which case
+    /// `eval` and `pdf` are zero everywhere.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    fn emitted(&self, _ray: &Ray, _hit_record: &HitRecord) -> Color {
+        Color::default()
+    }
+
+    /// Overall reflectance, written to the albedo AOV.
+    fn albedo(&self) -> Color {
+        Color::default()
+    }
 }
@@ -0,0 +1,232 @@
+use std::{f64::consts::PI, fs, io, path::Path, sync::Arc};
+
+use crate::vec::Vec3;
+
+/// Shape of the lens opening, which is also the shape of out of focus highlights.
+#[derive(Debug, Clone, Default)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    /// Regular polygon with `blades` corners on the unit circle, turned by
+    /// `rotation` degrees.
+    Polygon {
+        blades: u32,
+        rotation: f64,
+    },
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    /// Point on the aperture within the unit disk, uniform over its open area.
+    pub fn sample(&self, u: (f64, f64)) -> (f64, f64) {
+        match self {
+            Aperture::Circle => {
+                let point = Vec3::sample_concentric_disk(u);
+                (point.x, point.y)
+            }
+            Aperture::Polygon { blades, rotation } => sample_polygon(*blades, *rotation, u),
+            Aperture::Mask(mask) => mask.sample(u),
+        }
+    }
+}
+
+/// Picks one of the triangles between the center and two neighboring
+/// corners, all of the same area, and a uniform point inside it.
+fn sample_polygon(blades: u32, rotation: f64, (u0, u1): (f64, f64)) -> (f64, f64) {
+    let blades = blades.max(3);
+    let scaled = u0 * blades as f64;
+    let triangle = (scaled as u32).min(blades - 1);
+    let u0 = scaled - triangle as f64;
+
+    let corner = |i: u32| {
+        let angle = rotation.to_radians() + 2.0 * PI * i as f64 / blades as f64;
+        (angle.cos(), angle.sin())
+    };
+    let (a, b) = (corner(triangle), corner(triangle + 1));
+
+    // Uniform barycentric coordinates with the center as the third corner.
+    let s = u0.sqrt();
+    let (wa, wb) = (s * (1.0 - u1), s * u1);
+    (wa * a.0 + wb * b.0, wa * a.1 + wb * b.1)
+}
+
+/// Grayscale image of the aperture over `[-1, 1]²`, brighter pixels letting
+/// through more light.
+#[derive(Debug, Clone)]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    /// Cumulative sums of the pixels of every row, normalized to end at 1.
+    row_cdfs: Vec<Vec<f64>>,
+    /// Cumulative sums of the row totals, normalized to end at 1.
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    /// `values` are given row by row from the top, at least one must be positive.
+    pub fn new(width: usize, height: usize, values: &[f64]) -> Self {
+        assert!(width > 0 && height > 0, "aperture mask is empty");
+        assert_eq!(values.len(), width * height);
+        let (row_cdfs, row_sums): (Vec<_>, Vec<_>) = values
+            .chunks(width)
+            .map(|row| {
+                let cdf = cumulative(row);
+                let sum = *cdf.last().unwrap();
+                (normalize(cdf), sum)
+            })
+            .unzip();
+        let cdf = cumulative(&row_sums);
+        assert!(*cdf.last().unwrap() > 0.0, "aperture mask is black");
+
+        Self {
+            width,
+            height,
+            row_cdfs,
+            cdf: normalize(cdf),
+        }
+    }
+
+    /// Reads a binary or ASCII PGM image.
+    pub fn load_pgm(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let bytes = fs::read(path)?;
+
+        // The header is whitespace separated with comments from '#' to the line end.
+        let mut fields = Vec::new();
+        let mut position = 0;
+        while fields.len() < 4 && position < bytes.len() {
+            match bytes[position] {
+                b'#' => {
+                    while position < bytes.len() && bytes[position] != b'\n' {
+                        position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => position += 1,
+                _ => {
+                    let start = position;
+                    while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                        position += 1;
+                    }
+                    fields.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+                }
+            }
+        }
+        let [magic, width, height, max] = &fields[..] else {
+            return Err(invalid("truncated PGM header"));
+        };
+        let parse = |field: &String| field.parse::<usize>().map_err(|_| invalid("bad PGM size"));
+        let (width, height, max) = (parse(width)?, parse(height)?, parse(max)? as f64);
+        let size = width
+            .checked_mul(height)
+            .filter(|&size| size > 0 && max > 0.0)
+            .ok_or_else(|| invalid("bad PGM size"))?;
+
+        let values: Vec<f64> = match magic.as_str() {
+            "P5" if max < 256.0 => bytes
+                .get(position + 1..position + 1 + size)
+                .ok_or_else(|| invalid("truncated PGM data"))?
+                .iter()
+                .map(|&value| value as f64 / max)
+                .collect(),
+            "P2" => String::from_utf8_lossy(&bytes[position..])
+                .split_whitespace()
+                .map(|value| value.parse::<u32>().map(|value| value as f64 / max))
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid("bad PGM value"))?,
+            _ => return Err(invalid("only 8 bit P2 and P5 PGM images are supported")),
+        };
+        if values.len() != size {
+            return Err(invalid("truncated PGM data"));
+        }
+        if !values.iter().any(|&value| value > 0.0) {
+            return Err(invalid("aperture mask is black"));
+        }
+
+        Ok(Self::new(width, height, &values))
+    }
+
+    pub fn sample(&self, (u0, u1): (f64, f64)) -> (f64, f64) {
+        let (row, v) = sample_cdf(&self.cdf, u1);
+        let (column, u) = sample_cdf(&self.row_cdfs[row], u0);
+        let x = (column as f64 + u) / self.width as f64;
+        let y = (row as f64 + v) / self.height as f64;
+        (2.0 * x - 1.0, 1.0 - 2.0 * y)
+    }
+}
+
+fn cumulative(values: &[f64]) -> Vec<f64> {
+    let mut cdf = vec![0.0];
+    for value in values {
+        cdf.push(cdf.last().unwrap() + value.max(0.0));
+    }
+    cdf
+}
+
+fn normalize(cdf: Vec<f64>) -> Vec<f64> {
+    let total = *cdf.last().unwrap();
+    if total == 0.0 {
+        return cdf;
+    }
+    cdf.into_iter().map(|value| value / total).collect()
+}
+
+/// Index of the cell `u` falls into and the position inside it.
+fn sample_cdf(cdf: &[f64], u: f64) -> (usize, f64) {
+    let cell = (cdf.partition_point(|&value| value <= u) - 1).min(cdf.len() - 2);
+    let t = (u - cdf[cell]) / (cdf[cell + 1] - cdf[cell]);
+    (cell, t.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aperture_samples_stay_inside() {
+        let hexagon = Aperture::Polygon {
+            blades: 6,
+            rotation: 0.0,
+        };
+        // A mask letting light through its right half only.
+        let values: Vec<f64> = (0..16)
+            .map(|i| if i % 4 >= 2 { 1.0 } else { 0.0 })
+            .collect();
+        let mask = Aperture::Mask(Arc::new(ApertureMask::new(4, 4, &values)));
+
+        let n = 64;
+        let mut mask_mean = (0.0, 0.0);
+        for i in 0..n {
+            for j in 0..n {
+                let u = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+
+                let (x, y) = hexagon.sample(u);
+                let angle = y.atan2(x).rem_euclid(PI / 3.0) - PI / 6.0;
+                // Distance of the edge from the center along the sample direction.
+                let edge = (PI / 6.0).cos() / angle.cos();
+                assert!(x.hypot(y) <= edge + 1e-12);
+
+                let (x, y) = mask.sample(u);
+                assert!((0.0..=1.0).contains(&x) && y.abs() <= 1.0);
+                mask_mean = (mask_mean.0 + x, mask_mean.1 + y);
+            }
+        }
+
+        let count = (n * n) as f64;
+        assert!((mask_mean.0 / count - 0.5).abs() < 1e-3);
+        assert!((mask_mean.1 / count).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_invalid_masks_are_rejected() {
+        let path = std::env::temp_dir().join(format!("mask-{}.pgm", std::process::id()));
+        for image in ["P2 0 2 255\n", "P2 2 2 255\n0 0 0 0\n", "P2 2 2 255\n1 2\n"] {
+            fs::write(&path, image).unwrap();
+            let error = ApertureMask::load_pgm(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{image}");
+        }
+
+        fs::write(&path, "P2 2 1 255\n0 255\n").unwrap();
+        assert!(ApertureMask::load_pgm(&path).is_ok());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+use std::f64::consts::PI;
+
+use super::{CameraFrame, RayGenerator};
+use crate::{ray::Ray, vec::Onb};
+
+/// Full sphere of directions, longitude along the width and latitude along
+/// the height with the view direction in the center.
+#[derive(Debug, Clone)]
+pub struct Equirectangular {
+    frame: CameraFrame,
+    stereo: Option<f64>,
+}
+
+impl Equirectangular {
+    /// With `stereo` set to an interocular distance, the image holds an
+    /// omnidirectional stereo pair with the left eye on top.
+    pub fn new(frame: &CameraFrame, stereo: Option<f64>) -> Self {
+        Self {
+            frame: *frame,
+            stereo,
+        }
+    }
+}
+
+impl RayGenerator for Equirectangular {
+    fn generate_ray(&self, (x, y): (f64, f64), _lens: (f64, f64)) -> Option<Ray> {
+        let width = self.frame.image_width as f64;
+        let height = self.frame.image_height as f64;
+
+        // Over-under stereo stacks two panoramas of half the height.
+        let (y, height, eye) = match self.stereo {
+            Some(interocular) if y < height / 2.0 => (y, height / 2.0, -0.5 * interocular),
+            Some(interocular) => (y - height / 2.0, height / 2.0, 0.5 * interocular),
+            None => (y, height, 0.0),
+        };
+        let phi = (x / width - 0.5) * 2.0 * PI;
+        let theta = y / height * PI;
+
+        let Onb { u, v, w } = self.frame.basis;
+        let horizontal = phi.sin() * u - phi.cos() * w;
+        let direction = theta.sin() * horizontal + theta.cos() * v;
+        // Each eye sits on a circle, offset perpendicular to the horizontal direction.
+        let origin = self.frame.origin + eye * (phi.cos() * u + phi.sin() * w);
+
+        Some(Ray::new(origin, direction))
+    }
+}
@@ -0,0 +1,59 @@
+use super::{CameraFrame, RayGenerator};
+use crate::{ray::Ray, vec::Onb};
+
+/// How the angle to the view direction maps to the distance from the image center.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle.
+    #[default]
+    Equidistant,
+    /// Equal solid angles cover equal image areas.
+    Equisolid,
+}
+
+/// Circular fisheye filling the shorter side of the image.
+#[derive(Debug, Clone)]
+pub struct Fisheye {
+    frame: CameraFrame,
+    half_fov: f64,
+    mapping: FisheyeMapping,
+}
+
+impl Fisheye {
+    /// `fov` is in degrees and may exceed 180 for equidistant mapping.
+    pub fn new(frame: &CameraFrame, fov: f64, mapping: FisheyeMapping) -> Self {
+        Self {
+            frame: *frame,
+            half_fov: fov.to_radians() / 2.0,
+            mapping,
+        }
+    }
+}
+
+impl RayGenerator for Fisheye {
+    fn generate_ray(&self, (x, y): (f64, f64), _lens: (f64, f64)) -> Option<Ray> {
+        let (width, height) = (self.frame.image_width, self.frame.image_height);
+        let radius = width.min(height) as f64 / 2.0;
+        let dx = (x - width as f64 / 2.0) / radius;
+        let dy = (y - height as f64 / 2.0) / radius;
+        let r = (dx * dx + dy * dy).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * self.half_fov,
+            FisheyeMapping::Equisolid => 2.0 * (r * (self.half_fov / 2.0).sin()).asin(),
+        };
+
+        let Onb { u, v, w } = self.frame.basis;
+        let (cos_phi, sin_phi) = if r > 0.0 {
+            (dx / r, dy / r)
+        } else {
+            (1.0, 0.0)
+        };
+        let direction = theta.sin() * (cos_phi * u - sin_phi * v) - theta.cos() * w;
+
+        Some(Ray::new(self.frame.origin, direction))
+    }
+}
@@ -0,0 +1,470 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use super::{CameraFrame, RayGenerator};
+use crate::{
+    ray::Ray,
+    vec::{Onb, Vec3},
+};
+
+/// One surface of a lens description as found in lens patents, listed from
+/// the front of the lens to the film. Lengths are in millimetres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+    /// Radius of the spherical surface, positive if it bulges towards the
+    /// scene and 0 for the aperture stop.
+    pub curvature_radius: f64,
+    /// Distance along the axis to the next surface, for the last one the
+    /// distance to the film, which is replaced when focusing.
+    pub thickness: f64,
+    /// Index of refraction behind the surface, 0 meaning air.
+    pub eta: f64,
+    pub aperture_radius: f64,
+}
+
+impl LensElement {
+    const fn new(curvature_radius: f64, thickness: f64, eta: f64, aperture_diameter: f64) -> Self {
+        Self {
+            curvature_radius,
+            thickness,
+            eta,
+            aperture_radius: aperture_diameter / 2.0,
+        }
+    }
+
+    /// Reads a table with one surface per line giving the curvature radius,
+    /// thickness, index of refraction and aperture diameter, lines starting
+    /// with '#' being comments.
+    pub fn load_table(path: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "bad lens table line");
+        fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let values = line
+                    .split_whitespace()
+                    .map(|value| value.parse::<f64>().map_err(|_| invalid()))
+                    .collect::<io::Result<Vec<_>>>()?;
+                let [radius, thickness, eta, diameter] = values[..] else {
+                    return Err(invalid());
+                };
+                Ok(Self::new(radius, thickness, eta, diameter))
+            })
+            .collect()
+    }
+}
+
+/// Double Gauss 50mm f/2 lens, a common normal lens design.
+pub const DOUBLE_GAUSS_50MM: [LensElement; 11] = [
+    LensElement::new(29.475, 3.76, 1.67, 25.2),
+    LensElement::new(84.83, 0.12, 0.0, 25.2),
+    LensElement::new(19.275, 4.025, 1.67, 23.0),
+    LensElement::new(40.77, 3.275, 1.699, 23.0),
+    LensElement::new(12.75, 5.705, 0.0, 18.0),
+    LensElement::new(0.0, 4.5, 0.0, 17.1),
+    LensElement::new(-14.495, 1.18, 1.603, 17.0),
+    LensElement::new(40.77, 6.065, 1.658, 20.0),
+    LensElement::new(-20.385, 0.19, 0.0, 20.0),
+    LensElement::new(437.065, 3.22, 1.717, 20.0),
+    LensElement::new(-39.73, 5.0, 0.0, 20.0),
+];
+
+/// Why a lens description cannot be used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LensError {
+    NoSurfaces,
+    /// A ray close to the axis does not make it through the lens.
+    Blocked,
+    /// No film distance brings the focus distance into focus.
+    CannotFocus,
+}
+
+impl fmt::Display for LensError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            LensError::NoSurfaces => "a lens needs at least one surface",
+            LensError::Blocked => "rays along the axis are blocked by the lens",
+            LensError::CannotFocus => "the lens cannot focus at the focus distance",
+        };
+        f.write_str(message)
+    }
+}
+
+impl Error for LensError {}
+
+/// Rectangle of the rear element plane, as minimum and maximum corner.
+type Bounds = ((f64, f64), (f64, f64));
+
+/// Number of film radius intervals with their own exit pupil bounds.
+const PUPIL_INTERVALS: usize = 32;
+
+/// Camera tracing every ray through the surfaces of a real lens, giving the
+/// bokeh, vignetting and distortion of that lens. The film sits at the
+/// camera origin and a world unit is a metre.
+///
+/// Rays are traced from the film towards a bound of the exit pupil, the
+/// image of the aperture stop seen from the film, and samples blocked inside
+/// the lens stay black. Samples are not weighted by the pupil area, so the
+/// brightness only falls off with the vignetting.
+#[derive(Debug, Clone)]
+pub struct LensSystem {
+    frame: CameraFrame,
+    elements: Vec<LensElement>,
+    /// Film width and height in millimetres.
+    film_size: (f64, f64),
+    /// Exit pupil bounds for points on the positive x axis of the film, by
+    /// distance from the center, `None` where no ray gets through.
+    pupil_bounds: Vec<Option<Bounds>>,
+}
+
+/// The lens is traced in camera space, in millimetres with the film at
+/// `z = 0` and the lens towards positive `z`. The tables give the surfaces
+/// in lens space, which mirrors `z`.
+fn mirror(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.y, -v.z)
+}
+
+fn medium(eta: f64) -> f64 {
+    if eta == 0.0 {
+        1.0
+    } else {
+        eta
+    }
+}
+
+impl LensSystem {
+    /// `film_diagonal` is in millimetres, the lens is focused at
+    /// `focus_distance` metres from the film.
+    pub fn new(
+        frame: &CameraFrame,
+        elements: Vec<LensElement>,
+        film_diagonal: f64,
+        focus_distance: f64,
+    ) -> Result<Self, LensError> {
+        if elements.is_empty() {
+            return Err(LensError::NoSurfaces);
+        }
+        let aspect_ratio = frame.aspect_ratio();
+        let film_height = film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+
+        let mut lens = Self {
+            frame: *frame,
+            elements,
+            film_size: (film_height * aspect_ratio, film_height),
+            pupil_bounds: Vec::new(),
+        };
+        let film_distance = lens.focus_thick_lens(1000.0 * focus_distance)?;
+        lens.elements.last_mut().unwrap().thickness = film_distance;
+        lens.pupil_bounds = (0..PUPIL_INTERVALS)
+            .map(|i| {
+                let radius = 0.5 * film_diagonal / PUPIL_INTERVALS as f64;
+                lens.bound_exit_pupil(i as f64 * radius, (i + 1) as f64 * radius)
+            })
+            .collect();
+        Ok(lens)
+    }
+
+    fn rear_z(&self) -> f64 {
+        self.elements.last().unwrap().thickness
+    }
+
+    fn front_z(&self) -> f64 {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    /// Traces `ray` from the film side out of the front of the lens.
+    fn trace_from_film(&self, ray: &Ray) -> Option<Ray> {
+        let mut ray = Ray::new(mirror(ray.orig), mirror(ray.dir));
+        let mut element_z = 0.0;
+
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            element_z -= element.thickness;
+            let normal = self.intersect(element, element_z, &mut ray)?;
+
+            if let Some(normal) = normal {
+                let eta_i = medium(element.eta);
+                let eta_t = if i > 0 {
+                    medium(self.elements[i - 1].eta)
+                } else {
+                    1.0
+                };
+                ray.dir = refract(ray.dir.unit_vector(), normal, eta_i / eta_t)?;
+            } else if ray.dir.z >= 0.0 {
+                return None;
+            }
+        }
+
+        Some(Ray::new(mirror(ray.orig), mirror(ray.dir)))
+    }
+
+    /// Traces `ray` from the scene side out of the back of the lens.
+    fn trace_from_scene(&self, ray: &Ray) -> Option<Ray> {
+        let mut ray = Ray::new(mirror(ray.orig), mirror(ray.dir));
+        let mut element_z = -self.front_z();
+
+        for (i, element) in self.elements.iter().enumerate() {
+            let normal = self.intersect(element, element_z, &mut ray)?;
+
+            if let Some(normal) = normal {
+                let eta_i = if i > 0 {
+                    medium(self.elements[i - 1].eta)
+                } else {
+                    1.0
+                };
+                let eta_t = medium(element.eta);
+                ray.dir = refract(ray.dir.unit_vector(), normal, eta_i / eta_t)?;
+            }
+            element_z += element.thickness;
+        }
+
+        Some(Ray::new(mirror(ray.orig), mirror(ray.dir)))
+    }
+
+    /// Moves `ray` to its hit with the surface at `element_z` in lens space,
+    /// returning the normal facing the ray for refracting surfaces and
+    /// `None` for the stop, or nothing if the surface blocks the ray.
+    fn intersect(
+        &self,
+        element: &LensElement,
+        element_z: f64,
+        ray: &mut Ray,
+    ) -> Option<Option<Vec3>> {
+        let (t, normal) = if element.curvature_radius == 0.0 {
+            ((element_z - ray.orig.z) / ray.dir.z, None)
+        } else {
+            let radius = element.curvature_radius;
+            let (t, normal) = intersect_sphere(radius, element_z + radius, ray)?;
+            (t, Some(normal))
+        };
+
+        let hit = ray.at(t);
+        if hit.x * hit.x + hit.y * hit.y > element.aperture_radius * element.aperture_radius {
+            return None;
+        }
+        ray.orig = hit;
+        Some(normal)
+    }
+
+    /// Principal plane and focal point along the axis, traced with a ray
+    /// parallel to the axis from the scene and one from the film.
+    fn thick_lens(&self) -> Result<[(f64, f64); 2], LensError> {
+        let x = 0.001 * self.film_size.0.hypot(self.film_size.1);
+        let from_scene = Ray::new(
+            Vec3::new(x, 0.0, self.front_z() + 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        let from_film = Ray::new(
+            Vec3::new(x, 0.0, self.rear_z() - 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+
+        let cardinal_points = |ray_in: &Ray, ray_out: Option<Ray>| {
+            let ray_out = ray_out.ok_or(LensError::Blocked)?;
+            // In lens space, the scene lying towards negative z.
+            let focal_point = -ray_out.at(-ray_out.orig.x / ray_out.dir.x).z;
+            let principal_plane = -ray_out
+                .at((ray_in.orig.x - ray_out.orig.x) / ray_out.dir.x)
+                .z;
+            Ok((principal_plane, focal_point))
+        };
+        Ok([
+            cardinal_points(&from_scene, self.trace_from_scene(&from_scene))?,
+            cardinal_points(&from_film, self.trace_from_film(&from_film))?,
+        ])
+    }
+
+    /// Film distance behind the last surface focusing at `focus_distance`
+    /// millimetres, from the thick lens equation.
+    fn focus_thick_lens(&self, focus_distance: f64) -> Result<f64, LensError> {
+        let [(pz0, fz0), (pz1, _)] = self.thick_lens()?;
+        let f = fz0 - pz0;
+        let z = -focus_distance;
+        let c = (pz1 - z - pz0) * (pz1 - z - 4.0 * f - pz0);
+        // NaN for the infinite focal length of a lens without power.
+        if c.is_nan() || c <= 0.0 {
+            return Err(LensError::CannotFocus);
+        }
+        let delta = 0.5 * (pz1 - z + pz0 - c.sqrt());
+        Ok(self.rear_z() + delta)
+    }
+
+    /// Bounds of the points on the rear element plane that rays from film
+    /// points between `r0` and `r1` on the x axis pass the lens through.
+    fn bound_exit_pupil(&self, r0: f64, r1: f64) -> Option<Bounds> {
+        const FILM_SAMPLES: usize = 4;
+        const GRID: usize = 64;
+        let extent = 1.5 * self.elements.last().unwrap().aperture_radius;
+        let cell = 2.0 * extent / GRID as f64;
+
+        let mut bounds: Option<Bounds> = None;
+        for film_sample in 0..FILM_SAMPLES {
+            let r = r0 + (r1 - r0) * (film_sample as f64 + 0.5) / FILM_SAMPLES as f64;
+            let film = Vec3::new(r, 0.0, 0.0);
+            for i in 0..GRID {
+                for j in 0..GRID {
+                    let x = -extent + (i as f64 + 0.5) * cell;
+                    let y = -extent + (j as f64 + 0.5) * cell;
+                    let rear = Vec3::new(x, y, self.rear_z());
+                    if self.trace_from_film(&Ray::new(film, rear - film)).is_none() {
+                        continue;
+                    }
+                    let ((x0, y0), (x1, y1)) = bounds.unwrap_or(((x, y), (x, y)));
+                    bounds = Some(((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y))));
+                }
+            }
+        }
+
+        // The grid only finds points up to a cell inside the true bounds.
+        bounds.map(|((x0, y0), (x1, y1))| ((x0 - cell, y0 - cell), (x1 + cell, y1 + cell)))
+    }
+
+    /// Point on the rear element plane towards the exit pupil of `film`.
+    fn sample_exit_pupil(&self, film: Vec3, (u0, u1): (f64, f64)) -> Option<Vec3> {
+        let radius = film.x.hypot(film.y);
+        let half_diagonal = 0.5 * self.film_size.0.hypot(self.film_size.1);
+        let interval = (radius / half_diagonal * PUPIL_INTERVALS as f64) as usize;
+        let ((x0, y0), (x1, y1)) = self.pupil_bounds[interval.min(PUPIL_INTERVALS - 1)]?;
+        let (x, y) = (x0 + u0 * (x1 - x0), y0 + u1 * (y1 - y0));
+
+        // Rotate the bounds from the x axis to the film point.
+        let (sin, cos) = if radius > 0.0 {
+            (film.y / radius, film.x / radius)
+        } else {
+            (0.0, 1.0)
+        };
+        Some(Vec3::new(
+            cos * x - sin * y,
+            sin * x + cos * y,
+            self.rear_z(),
+        ))
+    }
+}
+
+impl RayGenerator for LensSystem {
+    fn generate_ray(&self, film: (f64, f64), lens: (f64, f64)) -> Option<Ray> {
+        // The lens flips the image, so the film is mirrored to keep it upright.
+        let x = film.0 / self.frame.image_width as f64 - 0.5;
+        let y = film.1 / self.frame.image_height as f64 - 0.5;
+        let film = Vec3::new(-x * self.film_size.0, y * self.film_size.1, 0.0);
+
+        let rear = self.sample_exit_pupil(film, lens)?;
+        let ray = self.trace_from_film(&Ray::new(film, rear - film))?;
+
+        let Onb { u, v, w } = self.frame.basis;
+        let to_world = |p: Vec3| p.x * u + p.y * v - p.z * w;
+        Some(Ray::new(
+            self.frame.origin + 0.001 * to_world(ray.orig),
+            to_world(ray.dir),
+        ))
+    }
+}
+
+/// Hit of `ray` with a sphere of `radius` centered on the axis at `z_center`
+/// and the normal there facing against the ray. Of the two hits the one on
+/// the side of the lens surface is taken.
+fn intersect_sphere(radius: f64, z_center: f64, ray: &Ray) -> Option<(f64, Vec3)> {
+    let origin = ray.orig - Vec3::new(0.0, 0.0, z_center);
+    let a = ray.dir.length_squared();
+    let b = 2.0 * ray.dir.dot(origin);
+    let c = origin.length_squared() - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // Numerically stable roots.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = (q / a, c / q);
+    let (t0, t1) = (t0.min(t1), t0.max(t1));
+    let t = if (ray.dir.z > 0.0) ^ (radius < 0.0) {
+        t0
+    } else {
+        t1
+    };
+    if t < 0.0 {
+        return None;
+    }
+
+    let normal = (origin + t * ray.dir).unit_vector();
+    let normal = if normal.dot(ray.dir) > 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    Some((t, normal))
+}
+
+/// Refraction of the unit `direction` at a surface with `normal` facing
+/// against it, `None` for total internal reflection.
+fn refract(direction: Vec3, normal: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = -direction.dot(normal);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(eta * direction + (eta * cos_i - cos_t) * normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lens_system_focuses() {
+        let frame = CameraFrame::new(
+            Vec3::default(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            36,
+            24,
+        );
+        let focus_distance = 2.0;
+        let lens =
+            LensSystem::new(&frame, DOUBLE_GAUSS_50MM.to_vec(), 43.27, focus_distance).unwrap();
+
+        // Rays through the whole pupil from a film point meet in the plane in focus.
+        for (film, target) in [((18.0, 12.0), (0.0, 0.0)), ((27.0, 6.0), (0.35, 0.23))] {
+            let mut hits = Vec::new();
+            for lens_sample in [(0.2, 0.5), (0.5, 0.2), (0.5, 0.5), (0.8, 0.5), (0.5, 0.8)] {
+                let Some(ray) = lens.generate_ray(film, lens_sample) else {
+                    continue;
+                };
+                hits.push(ray.at((-focus_distance - ray.orig.z) / ray.dir.z));
+            }
+            assert!(hits.len() >= 3);
+
+            for hit in &hits {
+                assert!((*hit - hits[0]).length() < 1e-3, "{hit:?} {:?}", hits[0]);
+                // Upright image, magnified like by a 50mm lens.
+                assert!((hit.x - target.0).abs() < 0.02, "{hit:?}");
+                assert!((hit.y - target.1).abs() < 0.02, "{hit:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unusable_lenses_are_rejected() {
+        let frame = CameraFrame::new(
+            Vec3::default(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            36,
+            24,
+        );
+        let lens = |elements: &[LensElement], focus_distance| {
+            LensSystem::new(&frame, elements.to_vec(), 43.27, focus_distance).err()
+        };
+
+        assert_eq!(lens(&[], 2.0), Some(LensError::NoSurfaces));
+        // Closer than the lens can focus, and a flat window without power.
+        assert_eq!(lens(&DOUBLE_GAUSS_50MM, 0.05), Some(LensError::CannotFocus));
+        let window = [
+            LensElement::new(0.0, 1.0, 1.5, 20.0),
+            LensElement::new(0.0, 5.0, 0.0, 20.0),
+        ];
+        assert_eq!(lens(&window, 2.0), Some(LensError::CannotFocus));
+        let pinhole = [LensElement::new(0.0, 5.0, 0.0, 0.0)];
+        assert_eq!(lens(&pinhole, 2.0), Some(LensError::Blocked));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::Debug;
+
+use crate::{
+    ray::Ray,
+    vec::{Onb, Vec3},
+};
+
+mod aperture;
+mod equirectangular;
+mod fisheye;
+mod lens_system;
+mod orthographic;
+mod perspective;
+
+pub use aperture::{Aperture, ApertureMask};
+pub use equirectangular::Equirectangular;
+pub use fisheye::{Fisheye, FisheyeMapping};
+pub use lens_system::{LensElement, LensError, LensSystem, DOUBLE_GAUSS_50MM};
+pub use orthographic::Orthographic;
+pub use perspective::Perspective;
+
+/// Turns positions on the film into camera rays, called by the renderer for
+/// every camera sample. Implement it to render with a custom projection.
+pub trait RayGenerator: Send + Sync + Debug {
+    /// Ray through `film`, measured in pixels from the top left corner of the
+    /// image, using `lens` to sample the aperture. `None` where the
+    /// projection does not cover the image, those samples stay black.
+    fn generate_ray(&self, film: (f64, f64), lens: (f64, f64)) -> Option<Ray>;
+}
+
+/// Position and orientation of the camera and the size of its image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraFrame {
+    pub origin: Vec3,
+    /// `u` points right, `v` up and `w` backwards.
+    pub basis: Onb,
+    pub image_width: u32,
+    pub image_height: u32,
+}
+
+impl CameraFrame {
+    pub fn new(
+        lookfrom: Vec3,
+        lookat: Vec3,
+        up: Vec3,
+        image_width: u32,
+        image_height: u32,
+    ) -> Self {
+        let w = (lookfrom - lookat).unit_vector();
+        let u = up.cross(w).unit_vector();
+        let v = w.cross(u);
+
+        Self {
+            origin: lookfrom,
+            basis: Onb { u, v, w },
+            image_width,
+            image_height,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.image_width as f64 / self.image_height as f64
+    }
+}
+
+/// Image plane at the focus distance in front of a thin lens, shared by the
+/// perspective and orthographic projections.
+#[derive(Debug, Clone)]
+struct ThinLens {
+    pixel_00_location: Vec3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    aperture: Aperture,
+    /// Shift of the clipping circle at the image corners in aperture radii.
+    cat_eye: f64,
+    image_center: (f64, f64),
+    half_diagonal: f64,
+    basis: Onb,
+    /// Center of the image plane before shifting.
+    focus_center: Vec3,
+    /// Normal of the plane in focus when it is tilted against the image plane.
+    focus_normal: Option<Vec3>,
+}
+
+impl ThinLens {
+    fn new(
+        frame: &CameraFrame,
+        viewport_height: f64,
+        focus_distance: f64,
+        defocus_angle: f64,
+    ) -> Self {
+        let Onb { u, v, w } = frame.basis;
+        let viewport_width = viewport_height * frame.aspect_ratio();
+
+        let viewport_u = u * viewport_width;
+        let viewport_v = -v * viewport_height;
+
+        let pixel_delta_u = viewport_u / frame.image_width as f64;
+        let pixel_delta_v = viewport_v / frame.image_height as f64;
+
+        let defocus_disk_radius = (defocus_angle / 2.0).to_radians().tan() * focus_distance;
+
+        let viewport_upper_left =
+            frame.origin - focus_distance * w - 0.5 * viewport_u - 0.5 * viewport_v;
+
+        Self {
+            pixel_00_location: viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v),
+            pixel_delta_u,
+            pixel_delta_v,
+            defocus_disk_u: defocus_disk_radius * u,
+            defocus_disk_v: defocus_disk_radius * v,
+            aperture: Aperture::default(),
+            cat_eye: 0.0,
+            image_center: (
+                0.5 * frame.image_width as f64,
+                0.5 * frame.image_height as f64,
+            ),
+            half_diagonal: 0.5 * (frame.image_width as f64).hypot(frame.image_height as f64),
+            basis: frame.basis,
+            focus_center: frame.origin - focus_distance * w,
+            focus_normal: None,
+        }
+    }
+
+    /// Moves the image window within the image plane by `(x, y)` times its
+    /// width and height, right and up.
+    fn shift(&mut self, (x, y): (f64, f64)) {
+        let viewport_u = self.pixel_delta_u * (2.0 * self.image_center.0);
+        let viewport_v = self.pixel_delta_v * (2.0 * self.image_center.1);
+        self.pixel_00_location += x * viewport_u - y * viewport_v;
+    }
+
+    /// Turns the plane in focus around its point straight ahead by `(x, y)`
+    /// degrees, around the horizontal axis moving its top and around
+    /// the vertical axis moving its right side away from the camera.
+    fn tilt(&mut self, (x, y): (f64, f64)) {
+        let Onb { u, v, w } = self.basis;
+        let (x, y) = (x.to_radians(), y.to_radians());
+        let up = v * x.cos() - w * x.sin();
+        let right = u * y.cos() - w * y.sin();
+        self.focus_normal = Some(right.cross(up).unit_vector());
+    }
+
+    /// Point in focus on the line from the lens center at `from` through the
+    /// image plane point `through`.
+    fn refocus(&self, from: Vec3, through: Vec3) -> Vec3 {
+        let Some(normal) = self.focus_normal else {
+            return through;
+        };
+        let t = (self.focus_center - from).dot(normal) / (through - from).dot(normal);
+        // Lines parallel to or pointing away from the tilted plane stay
+        // focused on the image plane.
+        if t.is_finite() && t > 0.0 {
+            from + t * (through - from)
+        } else {
+            through
+        }
+    }
+
+    /// Point of the image plane at the film position `(x, y)`.
+    fn focus_point(&self, (x, y): (f64, f64)) -> Vec3 {
+        self.pixel_00_location + self.pixel_delta_v * (y - 0.5) + self.pixel_delta_u * (x - 0.5)
+    }
+
+    /// Offset of the lens position sampled with `u` from the lens center,
+    /// `None` where the ray through `film` is blocked by the lens barrel.
+    fn lens_offset(&self, film: (f64, f64), u: (f64, f64)) -> Option<Vec3> {
+        let (x, y) = self.aperture.sample(u);
+
+        // Off axis the barrel cuts a second circle out of the aperture,
+        // shifted further the further the film position is from the center.
+        if self.cat_eye > 0.0 {
+            let shift = self.cat_eye / self.half_diagonal;
+            let shift_x = (film.0 - self.image_center.0) * shift;
+            let shift_y = (self.image_center.1 - film.1) * shift;
+            if (x - shift_x).hypot(y - shift_y) > 1.0 {
+                return None;
+            }
+        }
+
+        Some(x * self.defocus_disk_u + y * self.defocus_disk_v)
+    }
+}
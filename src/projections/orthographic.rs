@@ -0,0 +1,56 @@
+use super::{Aperture, CameraFrame, RayGenerator, ThinLens};
+use crate::{ray::Ray, vec::Vec3};
+
+/// Parallel rays along the view direction.
+#[derive(Debug, Clone)]
+pub struct Orthographic {
+    /// From the image plane back to the plane of the lens.
+    focus_offset: Vec3,
+    lens: ThinLens,
+}
+
+impl Orthographic {
+    /// `viewport_height` is the height of the visible area in world units.
+    pub fn new(
+        frame: &CameraFrame,
+        viewport_height: f64,
+        focus_distance: f64,
+        defocus_angle: f64,
+    ) -> Self {
+        Self {
+            focus_offset: focus_distance * frame.basis.w,
+            lens: ThinLens::new(frame, viewport_height, focus_distance, defocus_angle),
+        }
+    }
+
+    /// See `Perspective::with_aperture`.
+    pub fn with_aperture(mut self, aperture: Aperture, cat_eye: f64) -> Self {
+        self.lens.aperture = aperture;
+        self.lens.cat_eye = cat_eye;
+        self
+    }
+
+    /// See `Perspective::with_shift`.
+    pub fn with_shift(mut self, shift: (f64, f64)) -> Self {
+        self.lens.shift(shift);
+        self
+    }
+
+    /// See `Perspective::with_tilt`.
+    pub fn with_tilt(mut self, tilt: (f64, f64)) -> Self {
+        if tilt != (0.0, 0.0) {
+            self.lens.tilt(tilt);
+        }
+        self
+    }
+}
+
+impl RayGenerator for Orthographic {
+    fn generate_ray(&self, film: (f64, f64), lens: (f64, f64)) -> Option<Ray> {
+        let image_point = self.lens.focus_point(film);
+        let center = image_point + self.focus_offset;
+        let focus_point = self.lens.refocus(center, image_point);
+        let origin = center + self.lens.lens_offset(film, lens)?;
+        Some(Ray::new(origin, focus_point - origin))
+    }
+}
@@ -0,0 +1,55 @@
+use super::{Aperture, CameraFrame, RayGenerator, ThinLens};
+use crate::{ray::Ray, vec::Vec3};
+
+/// Pinhole or thin lens camera.
+#[derive(Debug, Clone)]
+pub struct Perspective {
+    origin: Vec3,
+    lens: ThinLens,
+}
+
+impl Perspective {
+    /// `fov` is the vertical field of view and `defocus_angle` the cone
+    /// angle of the rays through each point in focus, both in degrees.
+    pub fn new(frame: &CameraFrame, fov: f64, focus_distance: f64, defocus_angle: f64) -> Self {
+        let viewport_height = 2.0 * (fov.to_radians() / 2.0).tan() * focus_distance;
+        Self {
+            origin: frame.origin,
+            lens: ThinLens::new(frame, viewport_height, focus_distance, defocus_angle),
+        }
+    }
+
+    /// Shapes the defocus blur like `aperture` and clips it off axis to a
+    /// cat's eye, `cat_eye` being the offset of the clipping circle in the
+    /// image corners in aperture radii, 0 turning it off.
+    pub fn with_aperture(mut self, aperture: Aperture, cat_eye: f64) -> Self {
+        self.lens.aperture = aperture;
+        self.lens.cat_eye = cat_eye;
+        self
+    }
+
+    /// Off-axis projection with the image window shifted by `shift` times
+    /// its width and height, right and up, without turning the camera.
+    pub fn with_shift(mut self, shift: (f64, f64)) -> Self {
+        self.lens.shift(shift);
+        self
+    }
+
+    /// Tilts the plane in focus against the image plane like a tilt lens,
+    /// `tilt` degrees around the horizontal and vertical axes moving its top
+    /// and right side away from the camera.
+    pub fn with_tilt(mut self, tilt: (f64, f64)) -> Self {
+        if tilt != (0.0, 0.0) {
+            self.lens.tilt(tilt);
+        }
+        self
+    }
+}
+
+impl RayGenerator for Perspective {
+    fn generate_ray(&self, film: (f64, f64), lens: (f64, f64)) -> Option<Ray> {
+        let focus_point = self.lens.refocus(self.origin, self.lens.focus_point(film));
+        let origin = self.origin + self.lens.lens_offset(film, lens)?;
+        Some(Ray::new(origin, focus_point - origin))
+    }
+}
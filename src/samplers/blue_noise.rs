@@ -0,0 +1,213 @@
+use std::sync::OnceLock;
+
+use super::{hash, nested_uniform_scramble, sobol::sobol_2d, u32_to_unit, Sampler};
+
+const MASK_SIZE: usize = 64;
+const MASK_SIGMA: f64 = 1.5;
+
+/// Sobol points shared by all pixels, each pixel shifted by a blue noise
+/// mask so that the remaining error is distributed as blue noise over the
+/// image (Georgiev and Fajardo, "Blue-noise Dithered Sampling").
+#[derive(Debug, Clone)]
+pub struct BlueNoise {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl BlueNoise {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Mask value for the current pixel, using a different toroidal shift of
+    /// the mask for every dimension.
+    fn offset(&self, dimension: u32) -> f64 {
+        let h = hash(&[self.seed, dimension as u64, 1]);
+        let x = (self.pixel.0 as usize + h as usize) % MASK_SIZE;
+        let y = (self.pixel.1 as usize + (h >> 32) as usize) % MASK_SIZE;
+        mask()[y * MASK_SIZE + x]
+    }
+
+    fn sobol(&self) -> (f64, f64) {
+        let h = hash(&[self.seed, self.dimension as u64]);
+        let index = nested_uniform_scramble(self.index, h as u32);
+        let (x, y) = sobol_2d(index);
+        (
+            u32_to_unit(nested_uniform_scramble(x, (h >> 32) as u32)),
+            u32_to_unit(nested_uniform_scramble(y, hash(&[h]) as u32)),
+        )
+    }
+}
+
+impl Sampler for BlueNoise {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (x, _) = self.sobol();
+        let value = (x + self.offset(self.dimension)).fract();
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (x, y) = self.sobol();
+        let value = (
+            (x + self.offset(self.dimension)).fract(),
+            (y + self.offset(self.dimension + 1)).fract(),
+        );
+        self.dimension += 2;
+        value
+    }
+}
+
+/// Tileable blue noise ranks in [0, 1), built once with Ulichney's
+/// void-and-cluster method.
+fn mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(void_and_cluster)
+}
+
+/// Gaussian weighted density of the set points around every cell of the
+/// toroidal mask.
+#[derive(Clone)]
+struct Energy {
+    values: Vec<f64>,
+    kernel: Vec<(usize, usize, f64)>,
+}
+
+impl Energy {
+    fn new() -> Self {
+        let radius = (3.0 * MASK_SIGMA).ceil() as isize;
+        let size = MASK_SIZE as isize;
+        let kernel = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| {
+                let weight = (-((dx * dx + dy * dy) as f64) / (2.0 * MASK_SIGMA.powi(2))).exp();
+                (
+                    dx.rem_euclid(size) as usize,
+                    dy.rem_euclid(size) as usize,
+                    weight,
+                )
+            })
+            .collect();
+
+        Self {
+            values: vec![0.0; MASK_SIZE * MASK_SIZE],
+            kernel,
+        }
+    }
+
+    fn splat(&mut self, cell: usize, sign: f64) {
+        let (x, y) = (cell % MASK_SIZE, cell / MASK_SIZE);
+        for &(dx, dy, weight) in &self.kernel {
+            let index = ((y + dy) % MASK_SIZE) * MASK_SIZE + (x + dx) % MASK_SIZE;
+            self.values[index] += sign * weight;
+        }
+    }
+
+    /// Cell with the highest energy among the cells where `pattern` equals `value`.
+    fn tightest_cluster(&self, pattern: &[bool], value: bool) -> usize {
+        self.extreme(pattern, value, |a, b| a > b)
+    }
+
+    /// Cell with the lowest energy among the cells where `pattern` equals `value`.
+    fn largest_void(&self, pattern: &[bool], value: bool) -> usize {
+        self.extreme(pattern, value, |a, b| a < b)
+    }
+
+    fn extreme(&self, pattern: &[bool], value: bool, better: impl Fn(f64, f64) -> bool) -> usize {
+        let mut best = None;
+        for (cell, &energy) in self.values.iter().enumerate() {
+            if pattern[cell] != value {
+                continue;
+            }
+            match best {
+                Some((_, best_energy)) if !better(energy, best_energy) => {}
+                _ => best = Some((cell, energy)),
+            }
+        }
+        best.map(|(cell, _)| cell).unwrap_or_default()
+    }
+}
+
+fn void_and_cluster() -> Vec<f64> {
+    let cells = MASK_SIZE * MASK_SIZE;
+    let mut pattern = vec![false; cells];
+    let mut energy = Energy::new();
+
+    let initial_count = cells / 10;
+    let mut count = 0;
+    let mut k = 0;
+    while count < initial_count {
+        let cell = (hash(&[k]) % cells as u64) as usize;
+        k += 1;
+        if !pattern[cell] {
+            pattern[cell] = true;
+            energy.splat(cell, 1.0);
+            count += 1;
+        }
+    }
+
+    // Move points from the tightest cluster into the largest void until that
+    // no longer changes anything.
+    for _ in 0..cells {
+        let cluster = energy.tightest_cluster(&pattern, true);
+        pattern[cluster] = false;
+        energy.splat(cluster, -1.0);
+
+        let void = energy.largest_void(&pattern, false);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; cells];
+
+    let mut ones_pattern = pattern.clone();
+    let mut ones_energy = energy.clone();
+    for ones in (0..count).rev() {
+        let cluster = ones_energy.tightest_cluster(&ones_pattern, true);
+        ones_pattern[cluster] = false;
+        ones_energy.splat(cluster, -1.0);
+        rank[cluster] = ones;
+    }
+
+    while count < cells / 2 {
+        let void = energy.largest_void(&pattern, false);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+        rank[void] = count;
+        count += 1;
+    }
+
+    // Past half, the zeros are the minority, so fill their tightest clusters.
+    let mut zeros_energy = Energy::new();
+    for cell in (0..cells).filter(|&cell| !pattern[cell]) {
+        zeros_energy.splat(cell, 1.0);
+    }
+    while count < cells {
+        let cluster = zeros_energy.tightest_cluster(&pattern, false);
+        pattern[cluster] = true;
+        zeros_energy.splat(cluster, -1.0);
+        rank[cluster] = count;
+        count += 1;
+    }
+
+    rank.into_iter()
+        .map(|rank| (rank as f64 + 0.5) / cells as f64)
+        .collect()
+}
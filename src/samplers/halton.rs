@@ -0,0 +1,97 @@
+use std::sync::OnceLock;
+
+use super::{hash, hash_to_unit, mix_bits, permutation_element, Sampler, ONE_MINUS_EPSILON};
+
+const PRIME_COUNT: usize = 1000;
+
+/// Halton sequence with Owen-scrambled digits, using the sample index within
+/// the pixel and a separate scramble per pixel and dimension.
+#[derive(Debug, Clone)]
+pub struct Halton {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl Halton {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Halton {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.dimension as usize;
+        self.dimension += 1;
+
+        let h = hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            dimension as u64,
+        ]);
+        match primes().get(dimension) {
+            Some(&base) => owen_scrambled_radical_inverse(base, self.index as u64, h),
+            None => hash_to_unit(hash(&[h, self.index as u64])),
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+fn owen_scrambled_radical_inverse(base: u32, mut a: u64, hash: u64) -> f64 {
+    let base64 = base as u64;
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut value = 0.0;
+    let mut prefix = 0u64;
+
+    // Keep permuting digits, including the trailing zeros of `a`, until they
+    // no longer change the result.
+    while 1.0 - (base - 1) as f64 * inv_base_m < 1.0 {
+        let next = a / base64;
+        let digit = (a - next * base64) as u32;
+        let digit_hash = mix_bits(hash ^ prefix) as u32;
+        let digit = permutation_element(digit, base, digit_hash);
+
+        inv_base_m *= inv_base;
+        value += digit as f64 * inv_base_m;
+        prefix = prefix.wrapping_mul(base64).wrapping_add(digit as u64);
+        a = next;
+    }
+
+    value.min(ONE_MINUS_EPSILON)
+}
+
+fn primes() -> &'static [u32] {
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut primes = Vec::with_capacity(PRIME_COUNT);
+        let mut candidate = 2;
+        while primes.len() < PRIME_COUNT {
+            if primes
+                .iter()
+                .take_while(|&&p| p * p <= candidate)
+                .all(|&p| candidate % p != 0)
+            {
+                primes.push(candidate);
+            }
+            candidate += 1;
+        }
+        primes
+    })
+}
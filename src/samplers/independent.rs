@@ -0,0 +1,45 @@
+use super::{hash, hash_to_unit, Sampler};
+
+/// Uniform random values, each dimension hashed from the pixel and sample index.
+#[derive(Debug, Clone)]
+pub struct Independent {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl Independent {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for Independent {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let h = hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.index as u64,
+            self.dimension as u64,
+        ]);
+        self.dimension += 1;
+        hash_to_unit(h)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
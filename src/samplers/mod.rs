@@ -0,0 +1,201 @@
+mod blue_noise;
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+pub use blue_noise::BlueNoise;
+pub use halton::Halton;
+pub use independent::Independent;
+pub use sobol::Sobol;
+pub use stratified::Stratified;
+
+/// Source of the sample values consumed while tracing one camera sample.
+///
+/// Every call to `get_1d` or `get_2d` after `start_pixel_sample` moves on to
+/// the next dimension of the sample vector.
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32);
+
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64);
+
+    /// Position inside the pixel, the first dimensions of every sample.
+    fn get_pixel_2d(&mut self) -> (f64, f64) {
+        self.get_2d()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    #[default]
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub fn build(self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(Independent::new(seed)),
+            SamplerKind::Stratified => Box::new(Stratified::new(samples_per_pixel, seed)),
+            SamplerKind::Halton => Box::new(Halton::new(seed)),
+            SamplerKind::Sobol => Box::new(Sobol::new(seed)),
+            SamplerKind::BlueNoise => Box::new(BlueNoise::new(seed)),
+        }
+    }
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+pub(crate) fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
+}
+
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, v| {
+        mix_bits(h ^ v.wrapping_mul(0xbf58476d1ce4e5b9))
+    })
+}
+
+/// Maps the high bits of a hash to [0, 1).
+pub(crate) fn hash_to_unit(h: u64) -> f64 {
+    (h >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+pub(crate) fn u32_to_unit(x: u32) -> f64 {
+    (x as f64 / 4294967296.0).min(ONE_MINUS_EPSILON)
+}
+
+/// Element `i` of a pseudo-random permutation of `0..l` selected by `p`
+/// (Kensler, "Correlated Multi-Jittered Sampling").
+pub(crate) fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    i.wrapping_add(p) % l
+}
+
+/// Hash based Owen scrambling of the bits of `x` (Burley, "Practical
+/// Hash-based Owen Scrambling").
+pub(crate) fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fraction of the pixel covered by a disk, a smooth integrand with an edge.
+    fn pixel_error(sampler: &mut dyn Sampler, pixel: (u32, u32), samples: u32) -> f64 {
+        let center = (0.3 + 0.01 * pixel.0 as f64, 0.6 - 0.01 * pixel.1 as f64);
+        let mut covered = 0;
+        for index in 0..samples {
+            sampler.start_pixel_sample(pixel, index);
+            let (x, y) = sampler.get_pixel_2d();
+            if (x - center.0).powi(2) + (y - center.1).powi(2) < 0.16 {
+                covered += 1;
+            }
+        }
+
+        // Reference from a dense regular grid.
+        let n = 128;
+        let reference = (0..n * n)
+            .filter(|i| {
+                let x = ((i % n) as f64 + 0.5) / n as f64;
+                let y = ((i / n) as f64 + 0.5) / n as f64;
+                (x - center.0).powi(2) + (y - center.1).powi(2) < 0.16
+            })
+            .count() as f64
+            / (n * n) as f64;
+
+        covered as f64 / samples as f64 - reference
+    }
+
+    fn mean_squared_error(kind: SamplerKind) -> f64 {
+        let samples = 64;
+        let mut sampler = kind.build(samples, 7);
+        let pixels: Vec<_> = (0..8).flat_map(|x| (0..8).map(move |y| (x, y))).collect();
+        pixels
+            .iter()
+            .map(|pixel| pixel_error(sampler.as_mut(), *pixel, samples).powi(2))
+            .sum::<f64>()
+            / pixels.len() as f64
+    }
+
+    #[test]
+    fn test_samples_in_unit_interval() {
+        for kind in [
+            SamplerKind::Independent,
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+            SamplerKind::BlueNoise,
+        ] {
+            let mut sampler = kind.build(16, 3);
+            for index in 0..16 {
+                sampler.start_pixel_sample((5, 9), index);
+                for _ in 0..20 {
+                    let value = sampler.get_1d();
+                    assert!((0.0..1.0).contains(&value), "{kind:?} {value}");
+                    let (x, y) = sampler.get_2d();
+                    assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_low_discrepancy_samplers_reduce_error() {
+        let independent = mean_squared_error(SamplerKind::Independent);
+        for kind in [
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+            SamplerKind::BlueNoise,
+        ] {
+            let error = mean_squared_error(kind);
+            assert!(error < 0.5 * independent, "{kind:?} {error} {independent}");
+        }
+    }
+}
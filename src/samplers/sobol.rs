@@ -0,0 +1,79 @@
+use super::{hash, nested_uniform_scramble, u32_to_unit, Sampler};
+
+/// Owen-scrambled Sobol points, padded from the first two Sobol dimensions
+/// with an independently shuffled sample order for every dimension pair.
+#[derive(Debug, Clone)]
+pub struct Sobol {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl Sobol {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn dimension_hash(&self) -> u64 {
+        hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension as u64,
+        ])
+    }
+}
+
+impl Sampler for Sobol {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let h = self.dimension_hash();
+        self.dimension += 1;
+
+        let index = nested_uniform_scramble(self.index, h as u32);
+        let (x, _) = sobol_2d(index);
+        u32_to_unit(nested_uniform_scramble(x, (h >> 32) as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let h = self.dimension_hash();
+        self.dimension += 2;
+
+        let index = nested_uniform_scramble(self.index, h as u32);
+        let (x, y) = sobol_2d(index);
+        let y_seed = hash(&[h]);
+        (
+            u32_to_unit(nested_uniform_scramble(x, (h >> 32) as u32)),
+            u32_to_unit(nested_uniform_scramble(y, y_seed as u32)),
+        )
+    }
+}
+
+/// First two dimensions of the Sobol sequence as 32 bit fractions.
+pub(crate) fn sobol_2d(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+
+    let mut y = 0;
+    let mut direction = 1u32 << 31;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= direction;
+        }
+        bits >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    (x, y)
+}
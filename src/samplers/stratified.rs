@@ -0,0 +1,74 @@
+use super::{hash, hash_to_unit, permutation_element, Sampler};
+
+/// Jittered stratification, every dimension of a pixel's samples is split into
+/// `samples_per_pixel` strata visited in a per-dimension random order.
+#[derive(Debug, Clone)]
+pub struct Stratified {
+    samples_per_pixel: u32,
+    strata: (u32, u32),
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl Stratified {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let x_strata = ((samples_per_pixel as f64).sqrt() as u32).max(1);
+        let y_strata = samples_per_pixel.div_ceil(x_strata);
+
+        Self {
+            samples_per_pixel,
+            strata: (x_strata, y_strata),
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn dimension_hash(&self, salt: u64) -> u64 {
+        hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension as u64,
+            salt,
+        ])
+    }
+
+    fn jitter(&self, salt: u64) -> f64 {
+        hash_to_unit(hash(&[self.dimension_hash(salt), self.index as u64]))
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let count = self.samples_per_pixel;
+        let permutation = self.dimension_hash(0) as u32;
+        let stratum = permutation_element(self.index % count, count, permutation);
+        let value = (stratum as f64 + self.jitter(1)) / count as f64;
+
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (x_strata, y_strata) = self.strata;
+        let count = x_strata * y_strata;
+        let permutation = self.dimension_hash(0) as u32;
+        let stratum = permutation_element(self.index % count, count, permutation);
+        let x = ((stratum % x_strata) as f64 + self.jitter(1)) / x_strata as f64;
+        let y = ((stratum / x_strata) as f64 + self.jitter(2)) / y_strata as f64;
+
+        self.dimension += 2;
+        (x, y)
+    }
+}
@@ -0,0 +1,149 @@
+/// How the pixels of a pass are divided between the rayon workers.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scheduler {
+    /// Parallel over rows and, nested, over the pixels of every row.
+    #[default]
+    Rows,
+    /// Every worker renders whole tiles of `size` x `size` pixels, taking the
+    /// next one in `order` when it is done.
+    Tiles { size: u32, order: TileOrder },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TileOrder {
+    /// Row by row from the top left.
+    Scanline,
+    /// Outwards from the center of the image.
+    #[default]
+    Spiral,
+    /// Along a Hilbert curve, keeping consecutive tiles next to each other.
+    Hilbert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Tiles covering a `width` x `height` image, clipped at its borders.
+pub fn tiles(width: u32, height: u32, size: u32, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    let cells = match order {
+        TileOrder::Scanline => (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .collect(),
+        TileOrder::Spiral => spiral(columns, rows),
+        TileOrder::Hilbert => hilbert(columns, rows),
+    };
+
+    cells
+        .into_iter()
+        .map(|(x, y)| Tile {
+            x: x * size,
+            y: y * size,
+            width: size.min(width - x * size),
+            height: size.min(height - y * size),
+        })
+        .collect()
+}
+
+/// Walks a square spiral around the center cell, skipping cells outside the grid.
+fn spiral(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    if columns == 0 || rows == 0 {
+        return Vec::new();
+    }
+    let count = (columns * rows) as usize;
+    let mut cells = Vec::with_capacity(count);
+    let (mut x, mut y) = (((columns - 1) / 2) as i64, ((rows - 1) / 2) as i64);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut leg = 1;
+    let mut direction = 0;
+
+    cells.push((x as u32, y as u32));
+    while cells.len() < count {
+        // Legs grow by one every second turn: 1, 1, 2, 2, 3, 3, ...
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction % 4];
+            for _ in 0..leg {
+                x += dx;
+                y += dy;
+                if (0..columns as i64).contains(&x) && (0..rows as i64).contains(&y) {
+                    cells.push((x as u32, y as u32));
+                }
+            }
+            direction += 1;
+        }
+        leg += 1;
+    }
+
+    cells
+}
+
+/// Hilbert curve over the smallest power of two square containing the grid.
+fn hilbert(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    let n = columns.max(rows).next_power_of_two();
+    (0..n as u64 * n as u64)
+        .map(|d| hilbert_cell(n, d))
+        .filter(|&(x, y)| x < columns && y < rows)
+        .collect()
+}
+
+fn hilbert_cell(n: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0u32, 0u32);
+    let mut t = d;
+    let mut s = 1;
+    while s < n {
+        let rx = (1 & (t / 2)) as u32;
+        let ry = (1 & (t ^ rx as u64)) as u32;
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_image_once() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let (width, height) = (101, 37);
+            let mut covered = vec![0; (width * height) as usize];
+            for tile in tiles(width, height, 16, order) {
+                for y in tile.y..tile.y + tile.height {
+                    for x in tile.x..tile.x + tile.width {
+                        covered[(y * width + x) as usize] += 1;
+                    }
+                }
+            }
+
+            assert!(covered.iter().all(|&count| count == 1), "{order:?}");
+            assert!(tiles(0, height, 16, order).is_empty(), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn test_hilbert_steps_to_neighbors() {
+        let cells = hilbert(8, 8);
+        for pair in cells.windows(2) {
+            let distance = pair[0].0.abs_diff(pair[1].0) + pair[0].1.abs_diff(pair[1].1);
+            assert_eq!(distance, 1);
+        }
+    }
+}
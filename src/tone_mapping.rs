@@ -0,0 +1,168 @@
+use crate::vec::Color;
+
+/// Maps linear radiance to display values in [0, 1] before the sRGB encoding
+/// of the LDR images.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToneMapping {
+    /// Cuts off everything above 1.
+    #[default]
+    Clamp,
+    /// `L / (1 + L)` on the luminance, keeping the hue. Colors with a channel
+    /// still above 1 are desaturated at the same luminance.
+    Reinhard,
+    /// Reinhard that maps the luminance `white` and above to white instead
+    /// of approaching it at infinity.
+    ExtendedReinhard { white: f64 },
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output transforms.
+    Aces,
+    /// Troy Sobotka's AgX, desaturating bright colors like film does.
+    AgX,
+}
+
+impl ToneMapping {
+    pub fn apply(self, color: Color) -> Color {
+        match self {
+            ToneMapping::Clamp => color,
+            ToneMapping::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMapping::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMapping::Aces => aces(color),
+            ToneMapping::AgX => agx(color),
+        }
+    }
+}
+
+fn scale_luminance(color: Color, map: impl Fn(f64) -> f64) -> Color {
+    let luminance = color.luminance();
+    if luminance <= 0.0 {
+        return Color::default();
+    }
+    desaturate(color * (map(luminance) / luminance))
+}
+
+/// Moves `color` towards the gray of its luminance until no channel exceeds 1.
+fn desaturate(color: Color) -> Color {
+    let luminance = color.luminance();
+    let largest = color.x.max(color.y).max(color.z);
+    if largest <= 1.0 {
+        return color;
+    }
+    if luminance >= 1.0 {
+        return Color::new(1.0, 1.0, 1.0);
+    }
+    let gray = Color::new(luminance, luminance, luminance);
+    gray + (color - gray) * ((1.0 - luminance) / (largest - luminance))
+}
+
+/// Rows of a 3x3 matrix.
+fn transform(matrix: [[f64; 3]; 3], color: Color) -> Color {
+    let row = |r: [f64; 3]| r[0] * color.x + r[1] * color.y + r[2] * color.z;
+    Color::new(row(matrix[0]), row(matrix[1]), row(matrix[2]))
+}
+
+fn map_channels(color: Color, map: impl Fn(f64) -> f64) -> Color {
+    Color::new(map(color.x), map(color.y), map(color.z))
+}
+
+fn aces(color: Color) -> Color {
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let color = transform(INPUT, color);
+    let color = map_channels(color, |x| {
+        (x * (x + 0.0245786) - 0.000090537) / (x * (0.983729 * x + 0.4329510) + 0.238081)
+    });
+    map_channels(transform(OUTPUT, color), |x| x.clamp(0.0, 1.0))
+}
+
+fn agx(color: Color) -> Color {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let color = transform(INSET, color);
+    let color = map_channels(color, |x| {
+        let x = (x.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        // Polynomial approximation of the AgX base contrast curve.
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+
+    // The curve produces display values for a 2.2 gamma, linearize them again
+    // for the sRGB encoding.
+    map_channels(transform(OUTSET, color), |x| x.clamp(0.0, 1.0).powf(2.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operators_are_monotonic_and_bounded() {
+        let operators = [
+            ToneMapping::Reinhard,
+            ToneMapping::ExtendedReinhard { white: 200.0 },
+            ToneMapping::Aces,
+            ToneMapping::AgX,
+        ];
+
+        for operator in operators {
+            let mut previous = -1.0;
+            for i in 0..=100 {
+                let value = 0.01 * 1.1f64.powi(i);
+                let mapped = operator.apply(Color::new(value, value, value));
+                let luminance = mapped.luminance();
+                assert!(luminance >= previous, "{operator:?} at {value}");
+                assert!(mapped.x <= 1.0 + 1e-9 && mapped.x >= 0.0, "{operator:?}");
+                previous = luminance;
+            }
+        }
+
+        let white = ToneMapping::ExtendedReinhard { white: 4.0 };
+        let mapped = white.apply(Color::new(4.0, 4.0, 4.0));
+        assert!((mapped.x - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reinhard_keeps_saturated_colors_in_range() {
+        let red = Color::new(50.0, 0.5, 0.1);
+        for operator in [
+            ToneMapping::Reinhard,
+            ToneMapping::ExtendedReinhard { white: 20.0 },
+        ] {
+            let mapped = operator.apply(red);
+            for channel in [mapped.x, mapped.y, mapped.z] {
+                assert!(
+                    (0.0..=1.0 + 1e-9).contains(&channel),
+                    "{operator:?}: {mapped:?}"
+                );
+            }
+            // Still the reddest channel, at the luminance Reinhard maps to.
+            assert!(mapped.x > mapped.y && mapped.y > mapped.z);
+        }
+
+        let luminance = red.luminance();
+        let mapped = ToneMapping::Reinhard.apply(red);
+        assert!((mapped.luminance() - luminance / (1.0 + luminance)).abs() < 1e-9);
+    }
+}
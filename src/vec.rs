@@ -1,4 +1,7 @@
-use std::ops::Range;
+use std::{
+    f64::consts::{FRAC_PI_2, FRAC_PI_4, PI},
+    ops::Range,
+};
 
 use rand::Rng;
 
@@ -34,6 +37,10 @@ impl Vec3 {
         self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
     }
 
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     pub fn cross(self, rhs: Self) -> Self {
         Self {
             x: self.y * rhs.z - self.z * rhs.y,
@@ -43,8 +50,14 @@ impl Vec3 {
     }
 
     pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+        Self::random_with(&mut rand::thread_rng())
+    }
+
+    pub fn random_range(range: Range<f64>) -> Self {
+        Self::random_range_with(&mut rand::thread_rng(), range)
+    }
 
+    pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Self {
             x: rng.gen_range(0.0..1.0),
             y: rng.gen_range(0.0..1.0),
@@ -52,9 +65,7 @@ impl Vec3 {
         }
     }
 
-    pub fn random_range(range: Range<f64>) -> Self {
-        let mut rng = rand::thread_rng();
-
+    pub fn random_range_with<R: Rng + ?Sized>(rng: &mut R, range: Range<f64>) -> Self {
         Self {
             x: rng.gen_range(range.clone()),
             y: rng.gen_range(range.clone()),
@@ -62,33 +73,43 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
-        loop {
-            let vec = Self::random_range(-1.0..1.0);
-            let length = vec.length_squared();
-            if length < 1.0 && length > 1e-160 {
-                return vec / length.sqrt();
-            }
+    /// Maps `u` in [0, 1)^2 onto the unit disk in the xy plane, preserving
+    /// stratification (Shirley and Chiu's concentric mapping).
+    pub fn sample_concentric_disk(u: (f64, f64)) -> Self {
+        let (offset_x, offset_y) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+        if offset_x == 0.0 && offset_y == 0.0 {
+            return Self::default();
         }
+
+        let (r, theta) = if offset_x.abs() > offset_y.abs() {
+            (offset_x, FRAC_PI_4 * (offset_y / offset_x))
+        } else {
+            (offset_y, FRAC_PI_2 - FRAC_PI_4 * (offset_x / offset_y))
+        };
+        Self::new(r * theta.cos(), r * theta.sin(), 0.0)
     }
 
-    pub fn random_in_unit_circle() -> Self {
-        loop {
-            let mut vec = Self::random_range(-1.0..1.0);
-            vec.z = 0.0;
-            let length = vec.length_squared();
-            if length < 1.0 && length > 1e-160 {
-                return vec / length.sqrt();
-            }
-        }
+    /// Direction on the unit sphere, pdf 1 / (4 pi).
+    pub fn sample_uniform_sphere(u: (f64, f64)) -> Self {
+        let z = 1.0 - 2.0 * u.0;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        Self::new(r * phi.cos(), r * phi.sin(), z)
     }
 
-    pub fn random_on_hemisphere(normal: Vec3) -> Vec3 {
-        let vec = Self::random_in_unit_sphere();
-        match vec.dot(normal) > 0.0 {
-            true => vec,
-            false => -vec,
-        }
+    /// Direction in the +z hemisphere, pdf cos(theta) / pi.
+    pub fn sample_cosine_hemisphere(u: (f64, f64)) -> Self {
+        let d = Self::sample_concentric_disk(u);
+        let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+        Self::new(d.x, d.y, z)
+    }
+
+    /// Direction within `acos(cos_theta_max)` of +z, pdf 1 / (2 pi (1 - cos_theta_max)).
+    pub fn sample_uniform_cone(u: (f64, f64), cos_theta_max: f64) -> Self {
+        let cos_theta = (1.0 - u.0) + u.0 * cos_theta_max;
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        Self::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta)
     }
 
     pub fn near_zero(self) -> bool {
@@ -107,26 +128,60 @@ impl Vec3 {
         r_out_perp + r_out_parallel
     }
 
-    pub fn linear_to_gamme(self) -> Self {
-        Self {
-            x: self.x.sqrt(),
-            y: self.y.sqrt(),
-            z: self.z.sqrt(),
-        }
+    /// sRGB transfer function, for colors already mapped into [0, 1].
+    pub fn linear_to_srgb(self) -> Self {
+        let encode = |c: f64| {
+            if c <= 0.0031308 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        Self::new(encode(self.x), encode(self.y), encode(self.z))
     }
 
     pub fn to_bytes_string(self) -> String {
         let interval = Interval::new(0.0, 0.999);
-        let gamma = self.linear_to_gamme();
+        let srgb = self.linear_to_srgb();
         format!(
             "{} {} {}",
-            (interval.clamp(gamma.x) * 255.999) as i32,
-            (interval.clamp(gamma.y) * 255.999) as i32,
-            (interval.clamp(gamma.z) * 255.999) as i32
+            (interval.clamp(srgb.x) * 255.999) as i32,
+            (interval.clamp(srgb.y) * 255.999) as i32,
+            (interval.clamp(srgb.z) * 255.999) as i32
         )
     }
 }
 
+/// Orthonormal basis with `w` along a given direction, used to move samples
+/// generated around +z into world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let w = n.unit_vector();
+        let sign = 1.0_f64.copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+
+        Self {
+            u: Vec3::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x),
+            v: Vec3::new(b, sign + w.y * w.y * a, -w.y),
+            w,
+        }
+    }
+
+    /// Converts `local` from basis coordinates to world space.
+    pub fn transform(&self, local: Vec3) -> Vec3 {
+        local.x * self.u + local.y * self.v + local.z * self.w
+    }
+}
+
 impl std::ops::Add for Vec3 {
     type Output = Self;
 
@@ -323,4 +378,39 @@ mod tests {
 
         assert_eq!(v1.dot(v2), 32.0);
     }
+
+    #[test]
+    fn test_onb() {
+        for n in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, -3.0),
+        ] {
+            let onb = Onb::new(n);
+
+            assert!((onb.u.length() - 1.0).abs() < 1e-12);
+            assert!((onb.v.length() - 1.0).abs() < 1e-12);
+            assert!(onb.u.dot(onb.v).abs() < 1e-12);
+            assert!((onb.u.cross(onb.v) - onb.w).near_zero());
+            assert!((onb.transform(Vec3::new(0.0, 0.0, 1.0)) - n.unit_vector()).near_zero());
+        }
+    }
+
+    #[test]
+    fn test_samplers() {
+        for i in 0..16 {
+            for j in 0..16 {
+                let u = (i as f64 / 16.0, j as f64 / 16.0);
+
+                assert!(Vec3::sample_concentric_disk(u).length() <= 1.0);
+                assert!((Vec3::sample_uniform_sphere(u).length() - 1.0).abs() < 1e-12);
+
+                let hemisphere = Vec3::sample_cosine_hemisphere(u);
+                assert!(hemisphere.z >= 0.0);
+                assert!((hemisphere.length() - 1.0).abs() < 1e-12);
+
+                assert!(Vec3::sample_uniform_cone(u, 0.9).z >= 0.9 - 1e-12);
+            }
+        }
+    }
 }